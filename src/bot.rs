@@ -21,32 +21,36 @@ use anyhow::{anyhow, Context};
 use serenity::{
     async_trait,
     client::{Client, EventHandler},
-    http::Http,
+    http::{AttachmentType, Http},
     model::{
         gateway::Ready,
         interactions::{
             application_command::{ApplicationCommand, ApplicationCommandOptionType},
-            Interaction, InteractionResponseType,
+            Interaction, InteractionApplicationCommandCallbackDataFlags, InteractionResponseType,
         },
     },
 };
-use std::{env, fmt::Debug};
+use std::{borrow::Cow, env, fmt::Debug, sync::Arc};
 use tracing::{span, Level};
 
 use crate::{
     concepts::SameAs,
-    error::{ErrorExt, TriageTag},
-    executors::interaction_endpoint,
+    error::{denial_reply, ErrorExt, ErrorKind, TriageTag},
+    executors::{interaction_endpoint, response_visibility},
     global,
     global::CENTRAL,
     model::{
         request,
-        request::{Message, Request},
+        request::{Message, Request, Visibility},
+        response::Invoker,
     },
     parser::Parser,
 };
 use serenity::{
-    builder::{CreateEmbed, CreateInteractionResponse},
+    builder::{
+        CreateComponents, CreateEmbed, CreateInteractionResponse,
+        CreateInteractionResponseFollowup, EditInteractionResponse,
+    },
     model::interactions::{
         application_command::ApplicationCommandInteraction,
         message_component::MessageComponentInteraction,
@@ -146,6 +150,259 @@ impl Interactions {
         }
         Ok(())
     }
+
+    pub async fn edit_original_interaction_response<F>(
+        &self,
+        http: impl AsRef<Http>,
+        f: F,
+    ) -> anyhow::Result<()>
+    where
+        F: FnOnce(&mut EditInteractionResponse) -> &mut EditInteractionResponse,
+    {
+        match self {
+            Interactions::Command(command) => {
+                command.edit_original_interaction_response(http, f).await?;
+            }
+            Interactions::Component(component) => {
+                (*component)
+                    .edit_original_interaction_response(http, f)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn delete_original_interaction_response(
+        &self,
+        http: impl AsRef<Http>,
+    ) -> anyhow::Result<()> {
+        match self {
+            Interactions::Command(command) => {
+                command.delete_original_interaction_response(http).await?
+            }
+            Interactions::Component(component) => {
+                (*component)
+                    .delete_original_interaction_response(http)
+                    .await?
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn create_followup_message<'a, F>(
+        &self,
+        http: impl AsRef<Http>,
+        f: F,
+    ) -> anyhow::Result<()>
+    where
+        for<'b> F: FnOnce(
+            &'b mut CreateInteractionResponseFollowup<'a>,
+        ) -> &'b mut CreateInteractionResponseFollowup<'a>,
+    {
+        match self {
+            Interactions::Command(command) => {
+                command.create_followup_message(http, f).await?;
+            }
+            Interactions::Component(component) => {
+                (*component).create_followup_message(http, f).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Acknowledges the interaction, so that the executor can answer later.
+    ///
+    /// - Application commands: shows a loading state as the response, with `visibility`.
+    /// - Components: leaves the message that the component is attached to as is.
+    pub async fn defer(
+        &self,
+        http: impl AsRef<Http>,
+        visibility: Visibility,
+    ) -> anyhow::Result<()> {
+        match self {
+            Interactions::Command(_) => {
+                self.create_interaction_response(http, |response| {
+                    response.kind(InteractionResponseType::DeferredChannelMessageWithSource);
+                    if visibility == Visibility::Ephemeral {
+                        response.interaction_response_data(|data| {
+                            data.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL)
+                        });
+                    }
+                    response
+                })
+                .await
+            }
+            Interactions::Component(_) => {
+                self.create_interaction_response(http, |response| {
+                    response.kind(InteractionResponseType::DeferredUpdateMessage)
+                })
+                .await
+            }
+        }
+    }
+
+    /// Delivers `request` to the deferred interaction.
+    ///
+    /// - Application commands: edits the deferred response, which was deferred with `deferred`.
+    ///   A request with another visibility replaces it with a follow-up.
+    /// - Components: [Request::Update] and [Request::UpdateComponents] edit the message that
+    ///   the component is attached to, and the others are sent as follow-up messages.
+    /// - [Request::Attachments] replaces the deferred response with a follow-up,
+    ///   since files cannot be attached by editing.
+    pub async fn deliver(
+        &self,
+        http: impl AsRef<Http>,
+        deferred: Visibility,
+        request: Request,
+    ) -> anyhow::Result<String> {
+        let (visibility, request) = request.split_visibility();
+        let replace_original = matches!(self, Interactions::Command(_))
+            && (visibility != deferred || matches!(request, Request::Attachments { .. }));
+        let follow_up = match (self, &request) {
+            (Interactions::Command(_), _) => replace_original,
+            (Interactions::Component(_), Request::Update { .. })
+            | (Interactions::Component(_), Request::UpdateComponents(_)) => false,
+            (Interactions::Component(_), _) => true,
+        };
+        if replace_original {
+            self.delete_original_interaction_response(&http).await?;
+        }
+        let reply = Reply::from(request);
+        let log = format!("{reply:?}");
+        let sent = if follow_up {
+            self.create_followup_message(http, |message| reply.follow_up(message, visibility))
+                .await
+        } else {
+            self.edit_original_interaction_response(http, |response| reply.edit(response))
+                .await
+        };
+        sent.map(|_| format!(r#"{{ "response" => {log} }}"#))
+            .map_err(|err| anyhow!("http error: {} with {}", err, log))
+    }
+}
+
+/// Contents of a response, regardless of how it is delivered.
+#[derive(Debug, Default)]
+struct Reply {
+    content: String,
+    embeds: Vec<CreateEmbed>,
+    components: Vec<request::Component>,
+    files: Vec<request::Attachment>,
+    /// Leaves the content and embeds of the edited response as they are
+    keep_message: bool,
+}
+
+impl From<Message> for Reply {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::String(content) => Reply {
+                content,
+                ..Default::default()
+            },
+            Message::Embed(embed) => Reply {
+                embeds: vec![embed],
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl From<Request> for Reply {
+    fn from(request: Request) -> Self {
+        match request {
+            Request::Message(message) => Reply::from(message),
+            Request::WithVisibility(_, request) => Reply::from(*request),
+            Request::Components(component) => Reply {
+                content: match component {
+                    request::Component::Buttons(_) => "Hello Button.",
+                    request::Component::SelectMenu { .. } => {
+                        "I'll stabilize select menu when it's documented."
+                    }
+                }
+                .to_owned(),
+                components: vec![component],
+                ..Default::default()
+            },
+            Request::Interactive {
+                message,
+                components,
+            } => Reply {
+                components,
+                ..Reply::from(message)
+            },
+            Request::Attachments { message, files } => Reply {
+                files,
+                ..Reply::from(message)
+            },
+            Request::Update {
+                content,
+                embeds,
+                components,
+            } => Reply {
+                content,
+                embeds,
+                components,
+                ..Default::default()
+            },
+            Request::UpdateComponents(components) => Reply {
+                components,
+                keep_message: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+impl Reply {
+    /// Replaces the response with this reply, except the message if [Reply::keep_message].
+    fn edit(self, response: &mut EditInteractionResponse) -> &mut EditInteractionResponse {
+        if !self.keep_message {
+            // replace embeds instead of keeping the old ones
+            response
+                .0
+                .insert("embeds", serde_json::Value::Array(vec![]));
+            for embed in self.embeds {
+                response.add_embed(embed);
+            }
+            response.content(self.content);
+        }
+        let components = self.components;
+        response.components(|rows| {
+            for component in components {
+                create_action_row(rows, component);
+            }
+            rows
+        })
+    }
+
+    fn follow_up<'a, 'b>(
+        self,
+        message: &'b mut CreateInteractionResponseFollowup<'a>,
+        visibility: Visibility,
+    ) -> &'b mut CreateInteractionResponseFollowup<'a> {
+        if visibility == Visibility::Ephemeral {
+            message.flags(InteractionApplicationCommandCallbackDataFlags::EPHEMERAL);
+        }
+        if !self.content.is_empty() {
+            message.content(self.content);
+        }
+        for embed in self.embeds {
+            message.add_embed(embed);
+        }
+        for file in self.files {
+            message.add_file(AttachmentType::Bytes {
+                data: Cow::Owned(file.data),
+                filename: file.filename,
+            });
+        }
+        let components = self.components;
+        message.components(|rows| {
+            for component in components {
+                create_action_row(rows, component);
+            }
+            rows
+        })
+    }
 }
 
 #[async_trait]
@@ -155,6 +412,7 @@ impl EventHandler for Handler {
         let _enter = span.enter();
 
         tracing::event!(Level::INFO, "{} is connected!", ready.user.name);
+        let _ = global::HTTP.set(Arc::clone(&ctx.http));
         let interactions = ApplicationCommand::get_global_application_commands(&ctx.http)
             .await
             .map_err(|err| anyhow!("http error: {:?}", err))
@@ -165,152 +423,131 @@ impl EventHandler for Handler {
     }
 
     async fn interaction_create(&self, ctx: serenity::client::Context, interaction: Interaction) {
-        let result = {
-            if let Some(command) = interaction.clone().application_command() {
-                Some(
-                    command
-                        .data
-                        .parse()
-                        .and_then(|items| interaction_endpoint(&items))
-                        .map(|ok| (ok, Interactions::Command(command.clone())))
-                        .map_err(|err| (err, Interactions::Command(command.clone()))),
-                )
-            } else if let Some(component) = interaction.clone().message_component() {
-                Some(
-                    component
-                        .data
-                        .parse()
-                        .and_then(|items| interaction_endpoint(&items))
-                        .map(|ok| (ok, Interactions::Component(Box::new(component.clone()))))
-                        .map_err(|err| (err, Interactions::Component(Box::new(component.clone())))),
-                )
-            } else {
-                None
-            }
-        };
-        // un-expected interaction => skip
-        let result = if let Some(res) = result {
-            res
+        let (interactions, items, invoker) = if let Some(command) =
+            interaction.clone().application_command()
+        {
+            let items = command.data.parse();
+            let invoker = Invoker::new(&command.user, command.member.as_ref(), command.guild_id);
+            (Interactions::Command(command), items, invoker)
+        } else if let Some(component) = interaction.message_component() {
+            let items = component.data.parse();
+            let invoker = Invoker::new(
+                &component.user,
+                component.member.as_ref(),
+                component.guild_id,
+            );
+            (Interactions::Component(Box::new(component)), items, invoker)
         } else {
+            // un-expected interaction => skip
             return;
         };
-        match result {
-            Err((err, interactions)) => {
-                let mut embed = CreateEmbed::default();
-                embed
-                    .colour(Colour::RED)
-                    .title("INTERACTION ERROR:")
-                    .description(format!("{err:?}"));
 
-                let json = serde_json::to_string(&embed.0);
+        // Acknowledge first, so that slow executors can answer late instead of timing out.
+        let deferred = items
+            .as_ref()
+            .map_or(Visibility::Public, |items| response_visibility(items));
+        interactions
+            .defer(&ctx.http, deferred)
+            .await
+            .map(|_| r#"{ "response" => "deferred" }"#.to_owned())
+            .send_msg();
+
+        // Executors block on SQLite and mutexes, so run them outside of the async runtime.
+        let result = match items {
+            Ok(items) => {
+                tokio::task::spawn_blocking(move || interaction_endpoint(&items, &invoker))
+                    .await
+                    .unwrap_or_else(|err| Err(anyhow!("failed to join the executor: {}", err)))
+            }
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Err(err) => {
+                let reply = match denial_reply(&err) {
+                    Some(reply) => Message::String(reply),
+                    None => {
+                        let mut embed = CreateEmbed::default();
+                        embed
+                            .colour(Colour::RED)
+                            .title("INTERACTION ERROR:")
+                            .description(format!("{err:?}"));
+                        Message::Embed(embed)
+                    }
+                };
 
+                // Errors are only for the invoker.
                 interactions
-                    .create_interaction_response(&ctx.http, |response| {
-                        response
-                            .kind(InteractionResponseType::ChannelMessageWithSource)
-                            .interaction_response_data(|message| message.add_embed(embed))
-                    })
+                    .deliver(&ctx.http, deferred, Request::Message(reply).ephemeral())
                     .await
-                    .map(|_| format!(r#"{{ "response" => "{json:?}" }}"#))
-                    .map_err(|#[allow(unused)] err| anyhow!("http error: {err} with {json:?}"))
                     .send_msg();
 
-                let _ = CENTRAL
-                    .sender()
-                    .send(Msg::Issue {
-                        kind: err.kind().to_string(),
+                // Permission denials are expected, so they are not reported as issues.
+                let msg = match err.kind() {
+                    ErrorKind::PermissionError => Msg::Info {
+                        title: "Permission denied".to_owned(),
+                        description: Some(format!("{err:?}")),
+                    },
+                    kind => Msg::Issue {
+                        kind: kind.to_string(),
                         tag: err.triage().unwrap_or(TriageTag::NotBad),
                         cause: format!("{err:?}"),
                         backtrace: format!("{}", err.backtrace()),
-                    })
-                    .await;
+                    },
+                };
+                let _ = CENTRAL.sender().send(msg).await;
+            }
+            Ok(request) => {
+                interactions
+                    .deliver(&ctx.http, deferred, request)
+                    .await
+                    .send_msg();
             }
-            Ok((response, interactions)) => match response {
-                Request::Message(msg) => match msg {
-                    Message::String(msg) => {
-                        interactions
-                            .create_interaction_response(&ctx.http, |response| {
-                                response
-                                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                                    .interaction_response_data(|message| message.content(&msg))
-                            })
-                            .await
-                            .map(|_| format!(r#"{{ "response" => "{msg}" }}"#))
-                            .map_err(|#[allow(unused)] err| anyhow!("http error: {err} with {msg}"))
-                            .send_msg();
-                    }
-                    Message::Embed(embed) => {
-                        let json = serde_json::to_string(&embed.0);
-                        interactions
-                            .create_interaction_response(&ctx.http, |response| {
-                                response
-                                    .kind(InteractionResponseType::ChannelMessageWithSource)
-                                    .interaction_response_data(|message| message.add_embed(embed))
-                            })
-                            .await
-                            .map(|_| format!(r#"{{ "response" => {json:?}"#))
-                            .map_err(|err| anyhow!("http error: {} with {:?}", err, json))
-                            .send_msg();
-                    }
-                },
-                Request::Components(component) => {
-                    interactions
-                        .create_interaction_response(&ctx.http, |response| {
-                            response
-                                .kind(InteractionResponseType::ChannelMessageWithSource)
-                                .interaction_response_data(|data| match component {
-                                    request::Component::Buttons(buttons) => {
-                                        data.content("Hello Button.").components(|components| {
-                                            components.create_action_row(|action_row| {
-                                                for button in buttons.into_iter() {
-                                                    action_row.add_button(button);
-                                                }
-                                                action_row
-                                            })
-                                        })
-                                    }
-                                    request::Component::SelectMenu {
-                                        custom_id,
-                                        min_value,
-                                        max_value,
-                                        options,
-                                    } => data
-                                        .content("I'll stabilize select menu when it's documented.")
-                                        .components(|components| {
-                                            components.create_action_row(|act| {
-                                                act.create_select_menu(|select_menu| {
-                                                    select_menu
-                                                        .placeholder("選択肢がありません")
-                                                        .custom_id(custom_id)
-                                                        .min_values(min_value)
-                                                        .max_values(max_value)
-                                                        .options(|builder| {
-                                                            for opt in options {
-                                                                builder.create_option(|o| {
-                                                                    o.description(opt.description)
-                                                                        .value(opt.value)
-                                                                        .label(opt.label)
-                                                                });
-                                                            }
-                                                            builder
-                                                        })
-                                                })
-                                            })
-                                        }),
-                                })
-                        })
-                        .await
-                        .map_err(|err| anyhow!("http error: {}", err))
-                        .send_msg();
-                }
-                Request::Update { .. } => {
-                    // TODO
-                }
-            },
         }
     }
 }
 
+/// Appends `component` to `components` as a new action row.
+fn create_action_row(
+    components: &mut CreateComponents,
+    component: request::Component,
+) -> &mut CreateComponents {
+    match component {
+        request::Component::Buttons(buttons) => components.create_action_row(|action_row| {
+            for button in buttons.into_iter() {
+                action_row.add_button(button);
+            }
+            action_row
+        }),
+        request::Component::SelectMenu {
+            custom_id,
+            min_value,
+            max_value,
+            options,
+            disabled,
+        } => components.create_action_row(|act| {
+            act.create_select_menu(|select_menu| {
+                select_menu
+                    .placeholder("選択肢がありません")
+                    .custom_id(custom_id)
+                    .min_values(min_value)
+                    .max_values(max_value)
+                    .disabled(disabled)
+                    .options(|builder| {
+                        for opt in options {
+                            builder.create_option(|o| {
+                                o.description(opt.description)
+                                    .value(opt.value)
+                                    .label(opt.label)
+                            });
+                        }
+                        builder
+                    })
+            })
+        }),
+    }
+}
+
 /// # Prepare BOT Client
 /// 1. Read the configure toml file.
 /// 2. Read the discord token from environment variable `DISCORD_TOKEN`.
@@ -340,9 +577,9 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
     // ## sub-commands
     // - cat
     // - members
-    //     - set [member(s)]
-    //     - add [member]
-    //     - remove [member]
+    //     - set [user(s)] [mentions] [role]
+    //     - add [user(s)] [mentions] [role]
+    //     - remove [user(s)] [mentions] [role]
     // - range [lower] [upper]
     // - exclude
     //     - set [type] [item(s)]
@@ -397,7 +634,6 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                         o.name("user-1")
                             .description("member")
                             .kind(ApplicationCommandOptionType::User)
-                            .required(true)
                     })
                     .create_sub_option(|o| {
                         o.name("user-2")
@@ -414,6 +650,16 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                             .description("member")
                             .kind(ApplicationCommandOptionType::User)
                     })
+                    .create_sub_option(|o| {
+                        o.name("users")
+                            .description("any number of user mentions or IDs")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("role")
+                            .description("all members with the role")
+                            .kind(ApplicationCommandOptionType::Role)
+                    })
             })
             .create_option(|o| {
                 o.name("range")
@@ -444,7 +690,9 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                     })
                     .create_sub_option(|o| {
                         o.name("value")
-                            .description("item(s)")
+                            .description(
+                                "item(s) (quest: 5-1, 5-1..5-8, 6-*, with:<monster>, <title>)",
+                            )
                             .kind(ApplicationCommandOptionType::String)
                             .required(true)
                     })
@@ -473,7 +721,9 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                     })
                     .create_sub_option(|o| {
                         o.name("value")
-                            .description("item(s)")
+                            .description(
+                                "item(s) (quest: 5-1, 5-1..5-8, 6-*, with:<monster>, <title>)",
+                            )
                             .kind(ApplicationCommandOptionType::String)
                             .required(true)
                     })
@@ -492,6 +742,115 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                             .required(true)
                     })
             })
+            .create_option(|o| {
+                o.name("guest")
+                    .description("Manage guest members who are not Discord users")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("option")
+                            .description("add/remove/merge")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("add", "add")
+                            .add_string_choice("remove", "remove")
+                            .add_string_choice("merge", "merge")
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("name")
+                            .description("guest name")
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("user to merge the guest into")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+            })
+            .create_option(|o| {
+                o.name("history")
+                    .description("Shows recent configuration changes")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+            })
+            .create_option(|o| {
+                o.name("undo")
+                    .description("Reverts the last configuration change")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+            })
+            .create_option(|o| {
+                o.name("timezone")
+                    .description("Shows or sets the time zone of this server")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("zone")
+                            .description(
+                                "tz database name (e.g. Asia/Tokyo), or local for the host's time",
+                            )
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+            .create_option(|o| {
+                o.name("recency")
+                    .description("Shows or sets how many recent rolls generate avoids repeating")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("scope")
+                            .description("weapons of each member, or quests and monsters")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("weapons", "weapons")
+                            .add_string_choice("targets", "targets")
+                    })
+                    .create_sub_option(|o| {
+                        o.name("count")
+                            .description("number of recent rolls to avoid (0 disables it)")
+                            .kind(ApplicationCommandOptionType::Integer)
+                    })
+            })
+            .create_option(|o| {
+                o.name("weight")
+                    .description("Specify how often quests, monsters, or weapons are generated")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("type")
+                            .description("quest/monster/weapon")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("quest", "quest")
+                            .add_string_choice("monster", "monster")
+                            .add_string_choice("weapon", "weapon")
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("value")
+                            .description(
+                                "item(s) (quest: 5-1, 5-1..5-8, 6-*, with:<monster>, <title>)",
+                            )
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("weight")
+                            .description("e.g. 0.2 or x2 (1 resets it)")
+                            .kind(ApplicationCommandOptionType::String)
+                            .required(true)
+                    })
+            })
+            .create_option(|o| {
+                o.name("roles")
+                    .description("Shows or sets the roles allowed to change settings")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("kind")
+                            .description("admin or roulette manager")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("admin", "admin")
+                            .add_string_choice("manager", "manager")
+                    })
+                    .create_sub_option(|o| {
+                        o.name("role")
+                            .description("unsets the role if omitted")
+                            .kind(ApplicationCommandOptionType::Role)
+                    })
+            })
     })
     .await?;
 
@@ -501,6 +860,9 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
     // - quest
     // - monster
     //
+    // ## options
+    // - coverage
+    //
     let _ = ApplicationCommand::create_global_application_command(&http, |a| {
         a.name("generate")
             .description("generates a monster or quest members and weapons")
@@ -512,6 +874,11 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                     .add_string_choice("monster", "monster")
                     .required(true)
             })
+            .create_option(|o| {
+                o.name("coverage")
+                    .description("prioritizes weapons and monsters not cleared yet")
+                    .kind(ApplicationCommandOptionType::Boolean)
+            })
     })
     .await?;
 
@@ -520,6 +887,13 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
     // ## sub-commands
     //  - help
     //  - query
+    //  - compare
+    //  - monsters
+    //  - quests
+    //  - coverage
+    //  - export
+    //  - ranking
+    //  - activity
     let _ = ApplicationCommand::create_global_application_command(&http, |a| {
         a.name("statistics")
             .description("statistics query")
@@ -545,15 +919,293 @@ pub async fn prepare_bot_client() -> anyhow::Result<Client> {
                     })
                     .create_sub_option(|o| {
                         o.name("since")
-                            .description("YYYY-MM-DD")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("chart")
+                            .description("bar chart by default")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("bar", "bar")
+                            .add_string_choice("radar", "radar")
+                            .add_string_choice("fields", "fields")
+                    })
+            })
+            .create_option(|o| {
+                o.name("compare")
+                    .description("Comparing two hunters")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("a")
+                            .description("Choice a user")
+                            .kind(ApplicationCommandOptionType::User)
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("b")
+                            .description("Choice another user")
+                            .kind(ApplicationCommandOptionType::User)
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("weapon")
+                            .description("specify weapon key")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
                             .kind(ApplicationCommandOptionType::String)
                     })
                     .create_sub_option(|o| {
                         o.name("until")
-                            .description("YYYY-MM-DD")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
                             .kind(ApplicationCommandOptionType::String)
                     })
             })
+            .create_option(|o| {
+                o.name("monsters")
+                    .description("Most rolled monsters")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("all hunters if omitted")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+            .create_option(|o| {
+                o.name("quests")
+                    .description("Most rolled and never rolled quests")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+            .create_option(|o| {
+                o.name("coverage")
+                    .description("Coverage card of a hunter")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("yourself if omitted")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("basis")
+                            .description("cleared by default")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("assigned", "assigned")
+                            .add_string_choice("cleared", "cleared")
+                    })
+                    .create_sub_option(|o| {
+                        o.name("restrictions")
+                            .description("shows restrictions as well")
+                            .kind(ApplicationCommandOptionType::Boolean)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("monsters")
+                            .description("shows monsters as well")
+                            .kind(ApplicationCommandOptionType::Boolean)
+                    })
+            })
+            .create_option(|o| {
+                o.name("export")
+                    .description("Exporting logs and aggregates as a file")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("format")
+                            .description("file format")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("csv", "csv")
+                            .add_string_choice("json", "json")
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("all hunters if omitted")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("weapon")
+                            .description("specify weapon key")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+            .create_option(|o| {
+                o.name("ranking")
+                    .description("Ranking all hunters")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("by")
+                            .description("score to rank by")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("rolls", "rolls")
+                            .add_string_choice("weapon", "weapon")
+                            .add_string_choice("clears", "clears")
+                            .add_string_choice("variety", "variety")
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("weapon")
+                            .description("weapon key (ranking by weapon)")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+            .create_option(|o| {
+                o.name("activity")
+                    .description("Counting rolls per day, week or month")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("per")
+                            .description("period to count rolls per")
+                            .kind(ApplicationCommandOptionType::String)
+                            .add_string_choice("day", "day")
+                            .add_string_choice("week", "week")
+                            .add_string_choice("month", "month")
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("all hunters if omitted")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("since")
+                            .description("YYYY-MM-DD, or a relative period such as `last week`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("until")
+                            .description("YYYY-MM-DD, or a relative period such as `today`")
+                            .kind(ApplicationCommandOptionType::String)
+                    })
+            })
+    })
+    .await?;
+
+    // # quests command
+    //
+    // ## options
+    //  - rank (optional)
+    let _ = ApplicationCommand::create_global_application_command(&http, |a| {
+        a.name("quests")
+            .description("Browse the quest catalog")
+            .create_option(|o| {
+                o.name("rank")
+                    .description("quest rank")
+                    .kind(ApplicationCommandOptionType::Integer);
+                for (rank, quests) in global::QUESTS.iter().enumerate() {
+                    if !quests.is_empty() {
+                        o.add_int_choice(format!("★{rank}"), rank as i32);
+                    }
+                }
+                o
+            })
+    })
+    .await?;
+
+    // # roulette command
+    //
+    // ## sub-commands
+    // - join
+    // - leave
+    let _ = ApplicationCommand::create_global_application_command(&http, |a| {
+        a.name("roulette")
+            .description("Join or leave the roulette")
+            .create_option(|o| {
+                o.name("join")
+                    .description("Adds you to the members")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+            })
+            .create_option(|o| {
+                o.name("leave")
+                    .description("Removes you from the members")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+            })
+    })
+    .await?;
+
+    // # profile command
+    //
+    // ## sub-commands
+    // - set [hr] [rank]
+    // - show [user]
+    let _ = ApplicationCommand::create_global_application_command(&http, |a| {
+        a.name("profile")
+            .description("Hunter profile used to limit quest ranks")
+            .create_option(|o| {
+                o.name("set")
+                    .description("Stores your HR and highest unlocked star rank")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("hr")
+                            .description("hunter rank")
+                            .kind(ApplicationCommandOptionType::Integer)
+                            .required(true)
+                    })
+                    .create_sub_option(|o| {
+                        o.name("rank")
+                            .description("highest unlocked star rank")
+                            .kind(ApplicationCommandOptionType::Integer)
+                            .required(true);
+                        for star in 0..=7 {
+                            o.add_int_choice(format!("★{star}"), star);
+                        }
+                        o
+                    })
+            })
+            .create_option(|o| {
+                o.name("show")
+                    .description("Shows a profile")
+                    .kind(ApplicationCommandOptionType::SubCommand)
+                    .create_sub_option(|o| {
+                        o.name("user")
+                            .description("hunter (yourself by default)")
+                            .kind(ApplicationCommandOptionType::User)
+                    })
+            })
     })
     .await?;
 
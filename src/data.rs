@@ -17,14 +17,19 @@
  *
  */
 
-pub use config::{Config, Excluded, Settings, Target, TargetRank};
+pub use config::{
+    config_diff, is_valid_weight, Config, Excluded, Recency, Roles, Settings, Target, TargetRank,
+    Weights, MAX_RECENCY, MAX_WEIGHT,
+};
 pub use monsters::Monster;
 pub use objectives::{Objective, Order};
 pub use quests::{Quest, QuestID};
+pub use schema::{load_config, SCHEMA_VERSION};
 pub use weapon::Weapon;
 
 mod config;
 mod monsters;
 mod objectives;
 mod quests;
+mod schema;
 mod weapon;
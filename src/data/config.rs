@@ -18,14 +18,32 @@
  */
 
 use crate::data::{Monster, QuestID, Weapon};
+use itertools::Itertools;
 use serde_derive::{Deserialize, Serialize};
-use serenity::model::prelude::User;
-use std::collections::HashSet;
+use serenity::model::prelude::{RoleId, UserId};
+use std::collections::{BTreeMap, HashMap, HashSet};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Config {
-    pub members: HashSet<User>,
+    /// See [crate::data::SCHEMA_VERSION]
+    #[serde(default)]
+    pub schema_version: u32,
+    pub members: HashSet<UserId>,
     pub settings: Settings,
+    /// Added by the v0 -> v1 migration
+    pub roles: Roles,
+    /// Time zone names (e.g. `Asia/Tokyo`) keyed by guild ID
+    #[serde(default)]
+    pub time_zones: BTreeMap<String, String>,
+}
+
+/// Roles allowed to change settings.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Roles {
+    /// Role with the same authority as server administrators
+    pub admin: Option<RoleId>,
+    /// Optional "roulette manager" role
+    pub manager: Option<RoleId>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +51,24 @@ pub struct Settings {
     pub ranks: TargetRank,
     pub target: Target,
     pub excluded: Excluded,
+    /// Missing in configs written before anti-repeat was introduced
+    #[serde(default)]
+    pub recency: Recency,
+    /// Missing in configs written before weights were introduced
+    #[serde(default)]
+    pub weights: Weights,
+}
+
+/// Upper bound of [Recency] windows
+pub const MAX_RECENCY: usize = 20;
+
+/// Anti-repeat windows of `/generate`, where `0` disables them.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct Recency {
+    /// Number of the last weapons a member won't receive again
+    pub weapons: usize,
+    /// Number of the last quests or monsters the guild won't get again
+    pub targets: usize,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -53,3 +89,195 @@ pub struct Excluded {
     pub monster: HashSet<Monster>,
     pub weapon: HashSet<Weapon>,
 }
+
+/// Upper bound of [Weights]
+pub const MAX_WEIGHT: f64 = 100.0;
+
+/// Weights of `/generate` candidates keyed by their names (e.g. `5-12`, `rajang`, `bow`).
+///
+/// Candidates without weights weigh `1`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Weights {
+    #[serde(default)]
+    pub quest: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub monster: BTreeMap<String, f64>,
+    #[serde(default)]
+    pub weapon: BTreeMap<String, f64>,
+}
+
+impl Weights {
+    pub fn quest(&self, id: QuestID) -> f64 {
+        self.quest.get(&id.to_string()).copied().unwrap_or(1.0)
+    }
+
+    pub fn monster(&self, monster: Monster) -> f64 {
+        self.monster
+            .get(<&str>::from(monster))
+            .copied()
+            .unwrap_or(1.0)
+    }
+
+    pub fn weapon(&self, weapon: Weapon) -> f64 {
+        self.weapon
+            .get(<&str>::from(weapon))
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
+/// Whether `weight` can be stored in [Weights].
+pub fn is_valid_weight(weight: f64) -> bool {
+    weight > 0.0 && weight <= MAX_WEIGHT
+}
+
+/// Diff of two config TOML texts, where each line is keyed by its TOML path
+/// (e.g. `- settings.excluded.quest[] = [5, 12]`).
+///
+/// Arrays are compared as multisets, since sets are serialized in arbitrary order.
+/// Texts that are not valid TOML are compared line by line instead.
+pub fn config_diff(before: &str, after: &str) -> String {
+    match (before.parse::<toml::Value>(), after.parse::<toml::Value>()) {
+        (Ok(before), Ok(after)) => {
+            let mut lines = vec![];
+            diff_values("", &before, &after, &mut lines);
+            lines.join("\n")
+        }
+        _ => line_diff(before, after),
+    }
+}
+
+fn child_path(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{path}.{key}")
+    }
+}
+
+fn diff_values(path: &str, before: &toml::Value, after: &toml::Value, lines: &mut Vec<String>) {
+    use toml::Value;
+    match (before, after) {
+        (Value::Table(before), Value::Table(after)) => {
+            for key in before.keys().chain(after.keys()).sorted().dedup() {
+                let path = child_path(path, key);
+                match (before.get(key), after.get(key)) {
+                    (Some(before), Some(after)) => diff_values(&path, before, after, lines),
+                    (Some(before), None) => leaves(&path, before, '-', lines),
+                    (None, Some(after)) => leaves(&path, after, '+', lines),
+                    (None, None) => {}
+                }
+            }
+        }
+        (Value::Array(before), Value::Array(after)) => {
+            let before = before.iter().map(Value::to_string).counts();
+            let after = after.iter().map(Value::to_string).counts();
+            let changes =
+                |sign: char, from: &HashMap<String, usize>, to: &HashMap<String, usize>| {
+                    from.iter()
+                        .flat_map(|(value, n)| {
+                            std::iter::repeat(format!("{sign} {path}[] = {value}"))
+                                .take(n.saturating_sub(*to.get(value).unwrap_or(&0)))
+                        })
+                        .sorted()
+                        .collect_vec()
+                };
+            lines.extend(changes('-', &before, &after));
+            lines.extend(changes('+', &after, &before));
+        }
+        (before, after) if before != after => {
+            leaves(path, before, '-', lines);
+            leaves(path, after, '+', lines);
+        }
+        _ => {}
+    }
+}
+
+/// Lines of every value in `value` with `sign`.
+fn leaves(path: &str, value: &toml::Value, sign: char, lines: &mut Vec<String>) {
+    match value {
+        toml::Value::Table(table) => {
+            for (key, value) in table.iter().sorted_by(|a, b| a.0.cmp(b.0)) {
+                leaves(&child_path(path, key), value, sign, lines);
+            }
+        }
+        toml::Value::Array(values) => lines.extend(
+            values
+                .iter()
+                .map(|value| format!("{sign} {path}[] = {value}"))
+                .sorted(),
+        ),
+        value => lines.push(format!("{sign} {path} = {value}")),
+    }
+}
+
+/// Line-based diff of two texts.
+///
+/// Lines are compared regardless of their position.
+fn line_diff(before: &str, after: &str) -> String {
+    let before = before.lines().counts();
+    let after = after.lines().counts();
+    let removed = before.iter().flat_map(|(line, n)| {
+        std::iter::repeat(format!("- {line}"))
+            .take(n.saturating_sub(*after.get(line).unwrap_or(&0)))
+    });
+    let added = after.iter().flat_map(|(line, n)| {
+        std::iter::repeat(format!("+ {line}"))
+            .take(n.saturating_sub(*before.get(line).unwrap_or(&0)))
+    });
+    removed.sorted().chain(added.sorted()).join("\n")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn config_diff_test() {
+        let before = indoc::indoc! {r#"
+            members = [1, 2]
+
+            [settings.excluded]
+            quest = [[5, 12]]
+            monster = []
+
+            [settings.target]
+            quest = []
+            monster = ["rajang"]
+
+            [settings.recency]
+            weapons = 3
+        "#};
+
+        // Sets serialized in another order are unchanged.
+        let reordered = before.replace("[1, 2]", "[2, 1]");
+        assert_eq!(config_diff(before, &reordered), "");
+
+        // Moving a value between sections swaps lines, but is still a change.
+        let moved = before.replace("quest = [[5, 12]]", "quest = []").replace(
+            "quest = []\nmonster = [\"rajang\"]",
+            "quest = [[5, 12]]\nmonster = [\"rajang\"]",
+        );
+        assert_eq!(
+            config_diff(before, &moved),
+            indoc::indoc! {"
+                - settings.excluded.quest[] = [5, 12]
+                + settings.target.quest[] = [5, 12]"}
+        );
+
+        let changed = before.replace("weapons = 3", "weapons = 5");
+        assert_eq!(
+            config_diff(before, &changed),
+            "- settings.recency.weapons = 3\n+ settings.recency.weapons = 5"
+        );
+
+        let removed = before.replace("[settings.recency]\nweapons = 3\n", "");
+        assert_eq!(
+            config_diff(before, &removed),
+            "- settings.recency.weapons = 3"
+        );
+
+        // Broken TOML falls back to lines.
+        assert_eq!(config_diff("a = 1", "a = "), "- a = 1\n+ a = ");
+    }
+}
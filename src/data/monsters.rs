@@ -24,6 +24,8 @@ use strum_macros::{EnumIter, EnumProperty, EnumString, IntoStaticStr};
 
 #[derive(
     Debug,
+    Copy,
+    Clone,
     PartialEq,
     Eq,
     Hash,
@@ -17,11 +17,13 @@
  *
  */
 
+use crate::{data::Monster, global::QUESTS};
 use serde_derive::{Deserialize, Serialize};
+use strum::IntoEnumIterator;
 
 pub struct Quest(pub &'static str, pub &'static str);
 
-#[derive(Debug, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub struct QuestID(pub u32, pub u32);
 
 impl Quest {
@@ -32,4 +34,50 @@ impl Quest {
     pub fn objective(&self) -> &'static str {
         self.1
     }
+
+    /// Returns monsters appearing in the mandatory order.
+    ///
+    /// Each order is written as `<monster> ×<count>`; names that are not in [Monster] are skipped.
+    pub fn monsters(&self) -> Vec<Monster> {
+        let normalize = |name: &str| name.replace('・', "");
+        self.objective()
+            .split(',')
+            .map(|order| normalize(order.split('×').next().unwrap_or_default().trim()))
+            .filter_map(|name| Monster::iter().find(|monster| normalize(monster.ja()) == name))
+            .collect()
+    }
+}
+
+impl QuestID {
+    /// Looks up the quest in [QUESTS].
+    pub fn quest(&self) -> Option<&'static Quest> {
+        QUESTS
+            .get(self.0 as usize)
+            .and_then(|quests| quests.get(self.1 as usize))
+    }
+
+    /// Returns all quest IDs in the catalog, ordered by rank and number.
+    pub fn all() -> impl Iterator<Item = QuestID> {
+        QUESTS.iter().enumerate().flat_map(|(rank, quests)| {
+            (0..quests.len()).map(move |number| QuestID(rank as u32, number as u32))
+        })
+    }
+}
+
+impl std::fmt::Display for QuestID {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.0, self.1)
+    }
+}
+
+impl std::str::FromStr for QuestID {
+    type Err = anyhow::Error;
+
+    /// Parses `<rank>-<number>` without checking the catalog.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (rank, number) = s
+            .split_once('-')
+            .ok_or_else(|| anyhow::anyhow!("invalid quest ID: {}", s))?;
+        Ok(QuestID(rank.parse()?, number.parse()?))
+    }
 }
@@ -0,0 +1,365 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Schema versioning and validation of the config file.
+
+use crate::{
+    data::{is_valid_weight, Config, Monster, QuestID, Weapon, MAX_RECENCY},
+    global::QUESTS,
+};
+use anyhow::Context;
+use std::collections::BTreeMap;
+
+/// Current schema version of the config file
+pub const SCHEMA_VERSION: u32 = 2;
+
+/// Migration steps, where `MIGRATIONS[n]` migrates a config from version `n` to `n + 1`.
+const MIGRATIONS: [fn(&mut toml::value::Table) -> Vec<String>; SCHEMA_VERSION as usize] =
+    [v0_to_v1, v1_to_v2];
+
+/// v0 -> v1: adds the `roles` section.
+fn v0_to_v1(table: &mut toml::value::Table) -> Vec<String> {
+    if table.contains_key("roles") {
+        vec![]
+    } else {
+        table.insert(
+            "roles".to_string(),
+            toml::Value::Table(toml::value::Table::new()),
+        );
+        vec!["added empty `roles`".to_string()]
+    }
+}
+
+/// v1 -> v2: stores members by their IDs instead of the serialized users.
+///
+/// Bot accounts are dropped.
+fn v1_to_v2(table: &mut toml::value::Table) -> Vec<String> {
+    let mut report = vec![];
+    if let Some(toml::Value::Array(members)) = table.get_mut("members") {
+        *members = members
+            .drain(..)
+            .filter_map(|member| match member {
+                toml::Value::Table(user) => {
+                    let name = user
+                        .get("name")
+                        .and_then(toml::Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    if user.get("bot").and_then(toml::Value::as_bool) == Some(true) {
+                        report.push(format!("dropped bot member {name}"));
+                        None
+                    } else {
+                        user.get("id").cloned()
+                    }
+                }
+                id => Some(id),
+            })
+            .collect();
+        report.push("replaced members with their IDs".to_string());
+    }
+    report
+}
+
+/// Loads [Config] from TOML, migrating it to [SCHEMA_VERSION] and validating its references.
+///
+/// Returns the config and a report of migrations and dropped or fixed entries.
+pub fn load_config(content: &str) -> anyhow::Result<(Config, Vec<String>)> {
+    let mut value: toml::Value = toml::from_str(content)?;
+    let table = value
+        .as_table_mut()
+        .with_context(|| anyhow::anyhow!("config is not a table"))?;
+    let version = match table.get("schema_version") {
+        None => 0,
+        Some(version) => version
+            .as_integer()
+            .filter(|version| (0..=SCHEMA_VERSION as i64).contains(version))
+            .with_context(|| {
+                anyhow::anyhow!(
+                    "unsupported schema_version: {} (supported up to {})",
+                    version,
+                    SCHEMA_VERSION
+                )
+            })? as u32,
+    };
+
+    let mut report = vec![];
+    for (from, migration) in MIGRATIONS.iter().enumerate().skip(version as usize) {
+        report.extend(
+            migration(table)
+                .into_iter()
+                .map(|entry| format!("migration v{from} -> v{}: {entry}", from + 1)),
+        );
+    }
+    table.insert(
+        "schema_version".to_string(),
+        toml::Value::Integer(SCHEMA_VERSION as i64),
+    );
+
+    let mut config: Config = value.try_into()?;
+    report.extend(validate(&mut config));
+    Ok((config, report))
+}
+
+/// Drops or fixes entries that refer to nothing.
+fn validate(config: &mut Config) -> Vec<String> {
+    let mut report = vec![];
+    let settings = &mut config.settings;
+
+    for (section, quests) in [
+        ("target", &mut settings.target.quest),
+        ("excluded", &mut settings.excluded.quest),
+    ] {
+        quests.retain(|id| {
+            let exists = id.quest().is_some();
+            if !exists {
+                report.push(format!("dropped unknown quest {id} from {section}"));
+            }
+            exists
+        });
+    }
+
+    let ranks = &mut settings.ranks.ranks;
+    ranks.retain(|rank| {
+        let exists = *rank < QUESTS.len();
+        if !exists {
+            report.push(format!("dropped unknown rank ★{rank}"));
+        }
+        exists
+    });
+    let len = ranks.len();
+    ranks.sort_unstable();
+    ranks.dedup();
+    if ranks.len() != len {
+        report.push("dropped duplicated ranks".to_string());
+    }
+    if ranks.is_empty() {
+        report.push("no ranks are left; /generate has no quests to choose".to_string());
+    }
+
+    config.members.retain(|id| {
+        let valid = id.0 != 0;
+        if !valid {
+            report.push(format!("dropped invalid member {id}"));
+        }
+        valid
+    });
+
+    config.time_zones.retain(|guild_id, name| {
+        let valid = guild_id.parse::<u64>().is_ok() && name.parse::<chrono_tz::Tz>().is_ok();
+        if !valid {
+            report.push(format!(
+                "dropped invalid time zone {name} of guild {guild_id}"
+            ));
+        }
+        valid
+    });
+
+    let recency = &mut config.settings.recency;
+    for (name, window) in [
+        ("weapons", &mut recency.weapons),
+        ("targets", &mut recency.targets),
+    ] {
+        if *window > MAX_RECENCY {
+            report.push(format!(
+                "clamped recency.{name} from {window} to {MAX_RECENCY}"
+            ));
+            *window = MAX_RECENCY;
+        }
+    }
+
+    let weights = &mut config.settings.weights;
+    retain_weights("quest", &mut weights.quest, &mut report, |key| {
+        key.parse::<QuestID>()
+            .map_or(false, |id| id.quest().is_some())
+    });
+    retain_weights("monster", &mut weights.monster, &mut report, |key| {
+        key.parse::<Monster>().is_ok()
+    });
+    retain_weights("weapon", &mut weights.weapon, &mut report, |key| {
+        key.parse::<Weapon>().is_ok()
+    });
+    report
+}
+
+/// Drops weights of unknown keys or out of range from `section`.
+fn retain_weights(
+    section: &str,
+    weights: &mut BTreeMap<String, f64>,
+    report: &mut Vec<String>,
+    is_known: impl Fn(&str) -> bool,
+) {
+    weights.retain(|key, weight| {
+        let valid = is_known(key) && is_valid_weight(*weight);
+        if !valid {
+            report.push(format!(
+                "dropped invalid weight {key} = {weight} from {section}"
+            ));
+        }
+        valid
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use indoc::indoc;
+    use serenity::model::id::{RoleId, UserId};
+
+    const SETTINGS: &str = indoc! {r#"
+        [settings.ranks]
+        ranks = [3, 3, 99]
+
+        [settings.target]
+        quest = [[5, 12], [99, 1]]
+        monster = []
+        weapon = []
+
+        [settings.excluded]
+        quest = []
+        monster = []
+        weapon = []
+    "#};
+
+    fn members(config: &Config) -> Vec<UserId> {
+        let mut members = config.members.iter().copied().collect::<Vec<_>>();
+        members.sort_unstable();
+        members
+    }
+
+    #[test]
+    fn v0_test() {
+        let content = format!(
+            indoc! {r#"
+                [[members]]
+                id = 1
+                name = "hunter"
+                bot = false
+
+                [[members]]
+                id = 2
+                name = "roulette"
+                bot = true
+
+                {SETTINGS}
+            "#},
+            SETTINGS = SETTINGS
+        );
+        let (config, report) = load_config(&content).unwrap();
+        assert_eq!(config.schema_version, SCHEMA_VERSION);
+        assert_eq!(members(&config), vec![UserId(1)]);
+        assert!(config.roles.admin.is_none() && config.roles.manager.is_none());
+        assert_eq!(config.settings.ranks.ranks, vec![3]);
+        assert_eq!(config.settings.target.quest.len(), 1);
+        assert_eq!(
+            report,
+            vec![
+                "migration v0 -> v1: added empty `roles`",
+                "migration v1 -> v2: dropped bot member roulette",
+                "migration v1 -> v2: replaced members with their IDs",
+                "dropped unknown quest 99-1 from target",
+                "dropped unknown rank ★99",
+                "dropped duplicated ranks",
+            ]
+        );
+    }
+
+    #[test]
+    fn v1_test() {
+        let content = format!(
+            indoc! {r#"
+                schema_version = 1
+
+                [[members]]
+                id = 1
+                name = "hunter"
+
+                [roles]
+                admin = 10
+
+                {SETTINGS}
+            "#},
+            SETTINGS = SETTINGS
+        );
+        let (config, report) = load_config(&content).unwrap();
+        assert_eq!(config.schema_version, SCHEMA_VERSION);
+        assert_eq!(members(&config), vec![UserId(1)]);
+        assert_eq!(config.roles.admin, Some(RoleId(10)));
+        assert_eq!(
+            report[0],
+            "migration v1 -> v2: replaced members with their IDs"
+        );
+        assert!(report.iter().all(|entry| !entry.contains("v0 -> v1")));
+    }
+
+    #[test]
+    fn current_test() {
+        let content = indoc! {r#"
+            schema_version = 2
+            members = [1, 2]
+
+            [roles]
+
+            [settings.ranks]
+            ranks = [3, 4]
+
+            [settings.target]
+            quest = [[5, 12]]
+            monster = []
+            weapon = []
+
+            [settings.excluded]
+            quest = []
+            monster = []
+            weapon = []
+        "#};
+        let (config, report) = load_config(content).unwrap();
+        assert_eq!(members(&config), vec![UserId(1), UserId(2)]);
+        assert!(report.is_empty(), "{:?}", report);
+
+        // Serialized configs load back as they are.
+        let content = toml::to_string(&config).unwrap();
+        assert!(load_config(&content).unwrap().1.is_empty());
+    }
+
+    #[test]
+    fn future_test() {
+        let content = format!(
+            "schema_version = {}\nmembers = []\n[roles]\n{}",
+            SCHEMA_VERSION + 1,
+            SETTINGS
+        );
+        let err = load_config(&content).unwrap_err();
+        assert!(err.to_string().starts_with("unsupported schema_version"));
+    }
+
+    #[test]
+    fn empty_ranks_test() {
+        let content = format!(
+            "schema_version = {}\nmembers = []\n[roles]\n{}",
+            SCHEMA_VERSION,
+            SETTINGS.replace("[3, 3, 99]", "[99]")
+        );
+        let (config, report) = load_config(&content).unwrap();
+        assert!(config.settings.ranks.ranks.is_empty());
+        assert_eq!(
+            report.last().unwrap(),
+            "no ranks are left; /generate has no quests to choose"
+        );
+    }
+}
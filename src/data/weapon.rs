@@ -99,4 +99,9 @@ impl Weapon {
     pub fn ja(&self) -> &'static str {
         self.get_str("Japanese").unwrap()
     }
+
+    /// Returns true for restrictions, which are rolled like weapons.
+    pub fn is_restriction(&self) -> bool {
+        *self > Weapon::Bow
+    }
 }
@@ -31,6 +31,8 @@ pub enum ErrorKind {
     LogicError,
     /// [CommandError]
     CommandError,
+    /// [PermissionError]
+    PermissionError,
     /// Other errors
     Others,
 }
@@ -49,6 +51,9 @@ pub enum QueryError {
         #[source]
         source: chrono::format::ParseError,
     },
+    /// Used for unknown or reversed periods.
+    #[error("Invalid period: {param:?} => `{actual:?}`")]
+    InvalidPeriod { param: String, actual: String },
     /// Used at fail to INSERT or UPDATE.
     #[error("Failed to store data: {raw:?}\nwith query: {query:?}")]
     FailedToStore { raw: String, query: String },
@@ -90,6 +95,27 @@ pub enum CommandError {
     /// Used for invalid command arguments.
     #[error("Invalid argument : {arg:?}")]
     InvalidArgument { arg: String },
+    /// Used for quest selectors that match no quest in the catalog.
+    #[error("No such quest: {selector:?}")]
+    NoSuchQuest { selector: String },
+    /// Used when no candidate is left to generate from.
+    #[error("No candidates: {reason}")]
+    NoCandidates { reason: String },
+    /// Used when the last configuration change cannot be reverted.
+    #[error("Cannot undo: {reason}")]
+    CannotUndo { reason: String },
+}
+
+/// Error for permission denials.
+#[derive(Debug, Error)]
+pub enum PermissionError {
+    /// Used when the invoker lacks the authority required by the command.
+    #[error("{user} is not allowed to run {command:?} (requires {required})")]
+    Denied {
+        user: String,
+        command: String,
+        required: String,
+    },
 }
 
 /// Triage Sections for Error Level.
@@ -118,6 +144,7 @@ impl ErrorExt for QueryError {
     /// # Error Level Definition for QueryError
     /// - InvalidWeapon: NotBad
     /// - InvalidDate: NotBad
+    /// - InvalidPeriod: NotBad
     /// - FailedToStore: Delayed
     /// - FailedToAggregate: Immediate
     fn triage(&self) -> Option<TriageTag> {
@@ -125,6 +152,7 @@ impl ErrorExt for QueryError {
         Some(match self {
             InvalidWeapon { .. } => TriageTag::NotBad,
             InvalidDate { .. } => TriageTag::NotBad,
+            InvalidPeriod { .. } => TriageTag::NotBad,
             FailedToStore { .. } => TriageTag::Delayed,
             FailedToAggregate { .. } => TriageTag::Immediate,
         })
@@ -152,12 +180,18 @@ impl ErrorExt for CommandError {
     /// - TimeLimitExceeded: Immediate
     /// - FailedToSync: Immediate
     /// - InvalidArgument: NotBad
+    /// - NoSuchQuest: NotBad
+    /// - NoCandidates: NotBad
+    /// - CannotUndo: NotBad
     fn triage(&self) -> Option<TriageTag> {
         use CommandError::*;
         Some(match self {
             TimeLimitExceeded { .. } => TriageTag::Immediate,
             FailedToSync { .. } => TriageTag::Immediate,
             InvalidArgument { .. } => TriageTag::NotBad,
+            NoSuchQuest { .. } => TriageTag::NotBad,
+            NoCandidates { .. } => TriageTag::NotBad,
+            CannotUndo { .. } => TriageTag::NotBad,
         })
     }
 
@@ -166,6 +200,26 @@ impl ErrorExt for CommandError {
     }
 }
 
+impl ErrorExt for PermissionError {
+    /// # Error Level Definition for PermissionError
+    /// Not triaged: a denial is the expected behavior, not an anomaly of the BOT.
+    fn triage(&self) -> Option<TriageTag> {
+        None
+    }
+
+    fn kind(&self) -> ErrorKind {
+        ErrorKind::PermissionError
+    }
+}
+
+/// Reply to the invoker if `err` is a permission denial, which is expected and not an error
+/// of the BOT.
+pub fn denial_reply(err: &anyhow::Error) -> Option<String> {
+    err.chain()
+        .find_map(|cause| cause.downcast_ref::<PermissionError>())
+        .map(|denied| format!("Not permitted: {denied}"))
+}
+
 impl ErrorExt for anyhow::Error {
     /// Returns most fatal triage tag in error chain.
     fn triage(&self) -> Option<TriageTag> {
@@ -175,6 +229,9 @@ impl ErrorExt for anyhow::Error {
                     cause.downcast_ref::<QueryError>().map(ErrorExt::triage),
                     cause.downcast_ref::<LogicError>().map(ErrorExt::triage),
                     cause.downcast_ref::<CommandError>().map(ErrorExt::triage),
+                    cause
+                        .downcast_ref::<PermissionError>()
+                        .map(ErrorExt::triage),
                 ]
             })
             .max()
@@ -199,6 +256,12 @@ impl ErrorExt for anyhow::Error {
                         cause.downcast_ref::<CommandError>().map(ErrorExt::triage),
                         ErrorKind::CommandError,
                     ),
+                    (
+                        cause
+                            .downcast_ref::<PermissionError>()
+                            .map(ErrorExt::triage),
+                        ErrorKind::PermissionError,
+                    ),
                 ]
                 .iter()
                 .max_by_key(|(tag, _)| tag)
@@ -250,6 +313,17 @@ mod test {
                 .triage(),
             Some(TriageTag::Immediate)
         );
+
+        let denied = PermissionError::Denied {
+            user: "".to_string(),
+            command: "".to_string(),
+            required: "".to_string(),
+        };
+        assert_eq!(denied.triage(), None);
+        assert_eq!(
+            anyhow::Error::from(denied).context("context").triage(),
+            None
+        );
     }
 
     #[test]
@@ -279,5 +353,22 @@ mod test {
             query: "".to_string(),
         };
         assert_eq!(failed_to_aggregate.kind(), ErrorKind::QueryError);
+
+        let denied = PermissionError::Denied {
+            user: "".to_string(),
+            command: "".to_string(),
+            required: "".to_string(),
+        };
+        assert_eq!(denied.kind(), ErrorKind::PermissionError);
+        let denied = anyhow::Error::from(denied).context("context");
+        assert_eq!(denied.kind(), ErrorKind::PermissionError);
+        assert_eq!(
+            denial_reply(&denied).as_deref(),
+            Some(r#"Not permitted:  is not allowed to run "" (requires )"#)
+        );
+        assert_eq!(
+            denial_reply(&anyhow::Error::from(failed_to_aggregate)),
+            None
+        );
     }
 }
@@ -17,15 +17,35 @@
  *
  */
 
+mod activity;
+mod chart;
+mod confirmation;
+mod coverage;
 mod endpoint;
+mod export;
 mod generate;
+mod guest;
+mod history;
+mod permission;
+mod profile;
+mod quests;
+mod ranking;
+mod recency;
+mod rolls;
+mod roulette;
 mod settings;
 mod statistics;
+mod targets;
+mod time_zone;
 mod utility;
 mod version;
+mod weights;
 
-pub use endpoint::interaction_endpoint;
+pub use endpoint::{interaction_endpoint, response_visibility};
 pub use generate::generate;
+pub use profile::profile;
+pub use quests::quests;
+pub use roulette::roulette;
 pub use settings::settings;
 pub use statistics::statistics;
 pub use version::version;
@@ -0,0 +1,155 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Rolls per day, week or month of `/statistics activity`.
+
+use super::{
+    rolls,
+    statistics::{valid_period, Period},
+    time_zone::{bucket, local_datetime, time_zone, today, Zone},
+};
+use crate::{
+    error::QueryError,
+    global::CONN,
+    model::{
+        request::{Message, Request},
+        response::{Bucket, Invoker},
+    },
+};
+use roulette_macros::bailout;
+use serenity::{builder::CreateEmbed, model::user::User, utils::Colour};
+use thiserror::Error;
+
+/// Buckets shown at most, the latest ones
+const BUCKETS: usize = 30;
+
+/// Width of the longest bar
+const BAR: usize = 20;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error(
+        r#"
+        SELECT {bucket} AS bucket, COUNT(*) AS count FROM {table}
+            WHERE {conditions}
+            GROUP BY bucket
+            ORDER BY bucket
+    "#
+    )]
+    Activity {
+        bucket: String,
+        table: &'static str,
+        conditions: String,
+    },
+}
+
+/// Counts rolls of `user`, or of everyone, per bucket in `zone`.
+fn counts(
+    conn: &sqlite::Connection,
+    user: Option<&User>,
+    per: Bucket,
+    period: Period,
+    zone: Zone,
+) -> anyhow::Result<Vec<(String, usize)>> {
+    // Every member of a roll has a log, so rolls of everyone are counted in `rolls`.
+    let table = if user.is_some() { "logs" } else { "rolls" };
+    let column = format!("{table}.generated_at");
+    let mut conditions = vec!["1".to_string()];
+    if let Some(since) = period.0 {
+        conditions.push(format!(
+            "date({}) >= '{since}'",
+            local_datetime(&column, zone)
+        ));
+    }
+    if let Some(until) = period.1 {
+        conditions.push(format!(
+            "date({}) <= '{until}'",
+            local_datetime(&column, zone)
+        ));
+    }
+    if let Some(user) = user {
+        conditions.push(format!("logs.id = {}", user.id.0));
+    }
+    let query = Query::Activity {
+        bucket: bucket(&column, zone, per),
+        table,
+        conditions: conditions.join(" AND "),
+    };
+    let mut counts = vec![];
+    if let Err(err) = conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, Some(bucket)), (_, Some(count))] = pairs {
+            counts.push((bucket.to_string(), count.parse().unwrap_or_default()));
+        }
+        true
+    }) {
+        bailout!(
+            "query error",
+            QueryError::FailedToAggregate {
+                raw: format!("{err}"),
+                query: format!("{query}"),
+            }
+        );
+    }
+    Ok(counts)
+}
+
+/// Shows the number of rolls per day, week or month, of `user` or of everyone.
+pub(crate) fn activity(
+    user: Option<User>,
+    per: Bucket,
+    since: Option<String>,
+    until: Option<String>,
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
+    let zone = time_zone(invoker.guild_id);
+    let period = valid_period(since, until, today(zone))?;
+    let counts = {
+        let conn = CONN.lock().unwrap();
+        rolls::ensure_tables(&conn)?;
+        counts(&conn, user.as_ref(), per, period, zone)?
+    };
+    let max = counts
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or_default();
+    let lines = counts
+        .iter()
+        .skip(counts.len().saturating_sub(BUCKETS))
+        .map(|(bucket, count)| {
+            format!(
+                "`{bucket}` {bar} {count}",
+                bar = "▇".repeat((count * BAR + max - 1) / max.max(1)),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title(match &user {
+            Some(user) => format!("rolls of {} per {}", user.name, per.as_ref()),
+            None => format!("rolls per {}", per.as_ref()),
+        })
+        .description(if lines.is_empty() {
+            "No roll in the period.".to_string()
+        } else {
+            lines.join("\n")
+        })
+        .footer(|footer| footer.text(zone.name()));
+    Ok(Request::Message(Message::Embed(embed)))
+}
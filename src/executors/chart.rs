@@ -0,0 +1,179 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! PNG charts rendered locally with plotters.
+//!
+//! Labels are drawn with the font file at [CHART_FONT_PATH], which is read once and registered
+//! as [FONT]. No system font library is involved, and charts fail to render without the file.
+
+use crate::global::CHART_FONT_PATH;
+use anyhow::Context;
+use image::{png::PngEncoder, ColorType};
+use once_cell::sync::Lazy;
+use plotters::{
+    prelude::*,
+    style::text_anchor::{HPos, Pos, VPos},
+};
+use std::f64::consts::PI;
+
+/// Font family of titles and labels
+const FONT: &str = "sans-serif";
+const WIDTH: u32 = 800;
+/// Pink of the embeds ([serenity::utils::Colour::MEIBE_PINK])
+const PINK: RGBColor = RGBColor(0xe6, 0x88, 0xa1);
+
+/// Result of registering the font at [CHART_FONT_PATH] as [FONT]
+static FONT_REGISTERED: Lazy<Result<(), String>> = Lazy::new(|| {
+    let path = CHART_FONT_PATH
+        .as_ref()
+        .ok_or_else(|| "MHR_CHART_FONT is not set".to_string())?;
+    let bytes = std::fs::read(path).map_err(|err| format!("failed to read {path:?}: {err}"))?;
+    // plotters keeps the font for the rest of the process.
+    plotters::style::register_font(FONT, FontStyle::Normal, Box::leak(bytes.into_boxed_slice()))
+        .map_err(|_| format!("{path:?} is not a valid font"))
+});
+
+/// Fails if no font is available for charts, so that callers fall back to text.
+fn require_font() -> anyhow::Result<()> {
+    FONT_REGISTERED
+        .as_ref()
+        .map(|_| ())
+        .map_err(|err| anyhow::anyhow!("no font for charts: {}", err))
+}
+
+/// Renders a horizontal bar chart of `(label, value)`s into a PNG image.
+pub(crate) fn bar_chart(title: &str, bars: &[(&str, usize)]) -> anyhow::Result<Vec<u8>> {
+    require_font()?;
+    let height = 80 + 28 * bars.len().max(1) as u32;
+    let mut buffer = vec![0; (WIDTH * height * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (WIDTH, height)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let max = bars
+            .iter()
+            .map(|(_, value)| *value)
+            .max()
+            .unwrap_or_default();
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT, 24))
+            .margin(16)
+            .x_label_area_size(24)
+            .y_label_area_size(160)
+            .build_cartesian_2d(0..max + max / 10 + 1, (0..bars.len()).into_segmented())?;
+        chart
+            .configure_mesh()
+            .disable_y_mesh()
+            .y_labels(bars.len())
+            .y_label_formatter(&|y| match y {
+                SegmentValue::CenterOf(index) => bars
+                    .get(*index)
+                    .map(|(label, _)| label.to_string())
+                    .unwrap_or_default(),
+                _ => String::new(),
+            })
+            .label_style((FONT, 14))
+            .draw()?;
+        chart.draw_series(bars.iter().enumerate().map(|(index, (_, value))| {
+            let mut bar = Rectangle::new(
+                [
+                    (0, SegmentValue::Exact(index)),
+                    (*value, SegmentValue::Exact(index + 1)),
+                ],
+                PINK.filled(),
+            );
+            bar.set_margin(4, 4, 0, 0);
+            bar
+        }))?;
+        chart.draw_series(bars.iter().enumerate().map(|(index, (_, value))| {
+            Text::new(
+                value.to_string(),
+                (*value, SegmentValue::CenterOf(index)),
+                (FONT, 14).into_font(),
+            )
+        }))?;
+        root.present()?;
+    }
+    encode(buffer, WIDTH, height)
+}
+
+/// Renders a radar chart of `(label, value)`s into a PNG image.
+///
+/// Values are scaled to the largest one.
+pub(crate) fn radar_chart(title: &str, axes: &[(&str, usize)]) -> anyhow::Result<Vec<u8>> {
+    require_font()?;
+    let size = WIDTH;
+    let mut buffer = vec![0; (size * size * 3) as usize];
+    {
+        let root = BitMapBackend::with_buffer(&mut buffer, (size, size)).into_drawing_area();
+        root.fill(&WHITE)?;
+        let mut chart = ChartBuilder::on(&root)
+            .caption(title, (FONT, 24))
+            .margin(16)
+            .build_cartesian_2d(-1.4..1.4, -1.4..1.4)?;
+        let max = axes
+            .iter()
+            .map(|(_, value)| *value)
+            .max()
+            .unwrap_or_default()
+            .max(1);
+        // The first axis points upward, and the others follow clockwise.
+        let point = |index: usize, radius: f64| {
+            let angle = PI / 2.0 - 2.0 * PI * index as f64 / axes.len().max(1) as f64;
+            (radius * angle.cos(), radius * angle.sin())
+        };
+
+        for ring in 1..=4 {
+            let radius = ring as f64 / 4.0;
+            chart.draw_series(std::iter::once(PathElement::new(
+                (0..=axes.len())
+                    .map(|index| point(index, radius))
+                    .collect::<Vec<_>>(),
+                &BLACK.mix(0.2),
+            )))?;
+        }
+        chart.draw_series(
+            (0..axes.len()).map(|index| {
+                PathElement::new(vec![(0.0, 0.0), point(index, 1.0)], &BLACK.mix(0.2))
+            }),
+        )?;
+        chart.draw_series(std::iter::once(Polygon::new(
+            axes.iter()
+                .enumerate()
+                .map(|(index, (_, value))| point(index, *value as f64 / max as f64))
+                .collect::<Vec<_>>(),
+            PINK.mix(0.6).filled(),
+        )))?;
+        chart.draw_series(axes.iter().enumerate().map(|(index, (label, value))| {
+            Text::new(
+                format!("{label} {value}"),
+                point(index, 1.15),
+                TextStyle::from((FONT, 14).into_font()).pos(Pos::new(HPos::Center, VPos::Center)),
+            )
+        }))?;
+        root.present()?;
+    }
+    encode(buffer, size, size)
+}
+
+fn encode(buffer: Vec<u8>, width: u32, height: u32) -> anyhow::Result<Vec<u8>> {
+    let mut png = vec![];
+    PngEncoder::new(&mut png)
+        .encode(&buffer, width, height, ColorType::Rgb8)
+        .with_context(|| anyhow::anyhow!("failed to encode a chart"))?;
+    Ok(png)
+}
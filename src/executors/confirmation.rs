@@ -0,0 +1,122 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Confirmation dialogs for destructive operations.
+
+use crate::{
+    error::PermissionError,
+    model::{
+        request::{Buttons, Component, Message, Request},
+        response::{Choices, Invoker},
+    },
+};
+use once_cell::sync::Lazy;
+use roulette_macros::bailout;
+use serenity::{
+    builder::{CreateButton, CreateEmbed},
+    model::{id::UserId, interactions::message_component::ButtonStyle, user::User},
+};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long a confirmation waits for the answer.
+pub(crate) const CONFIRMATION_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Operation waiting for confirmation.
+#[derive(Debug)]
+pub(crate) enum Operation {
+    Obliterate(Choices),
+    SetMembers(Vec<User>),
+}
+
+#[derive(Debug)]
+struct Pending {
+    user: UserId,
+    operation: Operation,
+    requested_at: Instant,
+}
+
+/// Pending confirmations keyed by the token in custom_id of the buttons.
+static PENDING: Lazy<Mutex<HashMap<u64, Pending>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Holds `operation` until the invoker confirms it,
+/// and returns the `preview` with Confirm/Cancel buttons.
+pub(crate) fn ask(invoker: &Invoker, operation: Operation, mut preview: CreateEmbed) -> Request {
+    let token = rand::random::<u64>();
+    {
+        let mut pending = PENDING.lock().unwrap();
+        pending.retain(|_, pending| pending.requested_at.elapsed() < CONFIRMATION_TIMEOUT);
+        pending.insert(
+            token,
+            Pending {
+                user: invoker.user.id,
+                operation,
+                requested_at: Instant::now(),
+            },
+        );
+    }
+    preview.footer(|footer| {
+        footer.text(format!(
+            "This confirmation expires in {} seconds.",
+            CONFIRMATION_TIMEOUT.as_secs()
+        ))
+    });
+
+    let mut confirm = CreateButton::default();
+    confirm
+        .style(ButtonStyle::Danger)
+        .label("Confirm")
+        .custom_id(format!("confirm:{token}"));
+    let mut cancel = CreateButton::default();
+    cancel
+        .style(ButtonStyle::Secondary)
+        .label("Cancel")
+        .custom_id(format!("cancel:{token}"));
+
+    Request::Interactive {
+        message: Message::Embed(preview),
+        components: vec![Component::Buttons(Buttons::new(&[confirm, cancel]))],
+    }
+}
+
+/// Takes the operation for `token` out of the pending confirmations.
+///
+/// Returns `None` if the confirmation has expired (or the BOT has restarted since).
+/// Only the invoker of the original command can answer.
+pub(crate) fn take(token: u64, invoker: &Invoker) -> anyhow::Result<Option<Operation>> {
+    let mut pending = PENDING.lock().unwrap();
+    match pending.get(&token) {
+        Some(Pending { user, .. }) if *user != invoker.user.id => bailout!(
+            "permission denied",
+            PermissionError::Denied {
+                user: invoker.user.name.clone(),
+                command: "confirmation".to_string(),
+                required: "the requester".to_string(),
+            }
+        ),
+        _ => {}
+    }
+    Ok(pending
+        .remove(&token)
+        .filter(|pending| pending.requested_at.elapsed() < CONFIRMATION_TIMEOUT)
+        .map(|pending| pending.operation))
+}
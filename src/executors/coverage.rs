@@ -0,0 +1,304 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Coverage cards: weapons, restrictions and monsters each hunter has been assigned or cleared.
+
+use super::{
+    rolls::{self, Target},
+    utility::{failed_to_aggregate, hunter_names},
+};
+use crate::{
+    data::{Monster, Weapon},
+    global::CONN,
+    model::{
+        request::{Message, Request},
+        response::CoverageBasis,
+    },
+};
+use itertools::Itertools;
+use serenity::{
+    builder::CreateEmbed,
+    model::{id::UserId, user::User},
+    utils::Colour,
+};
+use sqlite::Connection;
+use std::collections::{HashMap, HashSet};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+/// Cells per row of the card
+const ROW: usize = 4;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error(
+        r#"
+        SELECT DISTINCT logs.id AS id, logs.weapon AS weapon, rolls.quest AS quest, rolls.monster AS monster
+            FROM logs LEFT JOIN rolls ON rolls.id = logs.roll_id
+            WHERE logs.id IN ({ids}) {cleared}
+    "#
+    )]
+    Covered { ids: String, cleared: &'static str },
+}
+
+/// Cells covered by a hunter.
+#[derive(Debug, Default)]
+pub(crate) struct Coverage {
+    /// Weapons and restrictions
+    pub weapons: HashSet<Weapon>,
+    pub monsters: HashSet<Monster>,
+}
+
+/// Returns the coverage of each hunter in `ids`.
+pub(crate) fn coverage(
+    conn: &Connection,
+    ids: &[UserId],
+    basis: CoverageBasis,
+) -> anyhow::Result<HashMap<UserId, Coverage>> {
+    rolls::ensure_tables(conn)?;
+    let mut coverage = HashMap::<UserId, Coverage>::new();
+    if ids.is_empty() {
+        return Ok(coverage);
+    }
+    let query = Query::Covered {
+        ids: ids.iter().map(|id| id.0.to_string()).join(", "),
+        cleared: match basis {
+            CoverageBasis::Assigned => "",
+            CoverageBasis::Cleared => "AND rolls.cleared = 1",
+        },
+    };
+    conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, Some(id)), (_, Some(weapon)), (_, quest), (_, monster)] = pairs {
+            if let Ok(id) = id.parse::<u64>() {
+                let cells = coverage.entry(UserId(id)).or_default();
+                cells.weapons.extend(weapon.parse::<Weapon>().ok());
+                if let Some(target) = Target::from_columns(*quest, *monster) {
+                    cells.monsters.extend(target.monsters());
+                }
+            }
+        }
+        true
+    })
+    .map_err(|err| failed_to_aggregate(&query, err))?;
+    Ok(coverage)
+}
+
+/// Weapons to prioritize for a hunter with `cells` covered.
+///
+/// Uncovered weapon types come first, then uncovered restrictions, and every weapon at last.
+pub(crate) fn uncovered_weapons(cells: Option<&Coverage>) -> Vec<Weapon> {
+    let uncovered = |restriction: bool| {
+        Weapon::iter()
+            .filter(|weapon| weapon.is_restriction() == restriction)
+            .filter(|weapon| cells.map_or(true, |cells| !cells.weapons.contains(weapon)))
+            .collect_vec()
+    };
+    let weapons = uncovered(false);
+    if !weapons.is_empty() {
+        return weapons;
+    }
+    let restrictions = uncovered(true);
+    if !restrictions.is_empty() {
+        return restrictions;
+    }
+    Weapon::iter().collect()
+}
+
+/// Monsters uncovered by the most of `members`, or every monster if all of them are covered.
+pub(crate) fn uncovered_monsters(
+    coverage: &HashMap<UserId, Coverage>,
+    members: &[UserId],
+) -> Vec<Monster> {
+    let uncovered_by = |monster: &Monster| {
+        members
+            .iter()
+            .filter(|member| {
+                coverage
+                    .get(member)
+                    .map_or(true, |cells| !cells.monsters.contains(monster))
+            })
+            .count()
+    };
+    let most = Monster::iter().map(|monster| uncovered_by(&monster)).max();
+    match most {
+        Some(most) if most > 0 => Monster::iter()
+            .filter(|monster| uncovered_by(monster) == most)
+            .collect(),
+        _ => Monster::iter().collect(),
+    }
+}
+
+/// Renders weapons in rows, marking covered ones.
+fn grid(weapons: &[Weapon], covered: impl Fn(&Weapon) -> bool) -> String {
+    weapons
+        .iter()
+        .map(|weapon| {
+            let mark = if covered(weapon) { "✅" } else { "⬜" };
+            format!("{mark}{}", weapon.ja())
+        })
+        .chunks(ROW)
+        .into_iter()
+        .map(|mut row| row.join("　"))
+        .join("\n")
+}
+
+/// Shows the coverage card of `user`.
+pub(crate) fn card(
+    user: User,
+    basis: CoverageBasis,
+    restrictions: bool,
+    monsters: bool,
+) -> anyhow::Result<Request> {
+    let (names, cells) = {
+        let conn = CONN.lock().unwrap();
+        let names = hunter_names(&conn, &[user.id])?;
+        let cells = coverage(&conn, &[user.id], basis)?
+            .remove(&user.id)
+            .unwrap_or_default();
+        (names, cells)
+    };
+    let name = names.get(&user.id).cloned().unwrap_or(user.name);
+    let weapons = Weapon::iter()
+        .filter(|weapon| !weapon.is_restriction())
+        .collect_vec();
+    let covered = |weapon: &Weapon| cells.weapons.contains(weapon);
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title(format!("{name}'s coverage card ({})", basis.as_ref()))
+        .field(
+            format!(
+                "weapons {}/{}",
+                weapons.iter().filter(|weapon| covered(weapon)).count(),
+                weapons.len()
+            ),
+            grid(&weapons, covered),
+            false,
+        );
+    if restrictions {
+        let restrictions = Weapon::iter().filter(Weapon::is_restriction).collect_vec();
+        embed.field(
+            format!(
+                "restrictions {}/{}",
+                restrictions.iter().filter(|weapon| covered(weapon)).count(),
+                restrictions.len()
+            ),
+            grid(&restrictions, covered),
+            false,
+        );
+    }
+    if monsters {
+        let all = Monster::iter().collect_vec();
+        let uncovered = all
+            .iter()
+            .filter(|monster| !cells.monsters.contains(monster))
+            .map(|monster| monster.ja())
+            .collect_vec();
+        embed.field(
+            format!("monsters {}/{}", all.len() - uncovered.len(), all.len()),
+            if uncovered.is_empty() {
+                "all covered 🎉".to_string()
+            } else {
+                format!(
+                    "uncovered: {}",
+                    uncovered.join(" ").chars().take(1000).collect::<String>()
+                )
+            },
+            false,
+        );
+    }
+    Ok(Request::Message(Message::Embed(embed)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn uncovered_weapons_test() {
+        assert_eq!(
+            uncovered_weapons(None),
+            Weapon::iter()
+                .filter(|weapon| !weapon.is_restriction())
+                .collect_vec()
+        );
+
+        let mut cells = Coverage::default();
+        cells.weapons.insert(Weapon::Bow);
+        let weapons = uncovered_weapons(Some(&cells));
+        assert!(!weapons.contains(&Weapon::Bow));
+        assert!(weapons.iter().all(|weapon| !weapon.is_restriction()));
+
+        cells
+            .weapons
+            .extend(Weapon::iter().filter(|weapon| !weapon.is_restriction()));
+        let weapons = uncovered_weapons(Some(&cells));
+        assert!(!weapons.is_empty());
+        assert!(weapons.iter().all(Weapon::is_restriction));
+
+        cells.weapons.extend(Weapon::iter());
+        assert_eq!(
+            uncovered_weapons(Some(&cells)),
+            Weapon::iter().collect_vec()
+        );
+    }
+
+    #[test]
+    fn uncovered_monsters_test() {
+        let members = [UserId(1), UserId(2)];
+        let mut coverage = HashMap::<UserId, Coverage>::new();
+        assert_eq!(
+            uncovered_monsters(&coverage, &members),
+            Monster::iter().collect_vec()
+        );
+
+        let all = Monster::iter().collect_vec();
+        let (first, rest) = all.split_first().unwrap();
+        coverage
+            .entry(members[0])
+            .or_default()
+            .monsters
+            .extend(rest.iter().copied());
+        coverage
+            .entry(members[1])
+            .or_default()
+            .monsters
+            .extend(rest.iter().skip(1).copied());
+        assert_eq!(uncovered_monsters(&coverage, &members), vec![*first]);
+
+        coverage
+            .entry(members[0])
+            .or_default()
+            .monsters
+            .insert(*first);
+        assert_eq!(
+            uncovered_monsters(&coverage, &members),
+            vec![*first, rest[0]]
+        );
+
+        for member in members.iter() {
+            coverage
+                .entry(*member)
+                .or_default()
+                .monsters
+                .extend(all.iter().copied());
+        }
+        assert_eq!(uncovered_monsters(&coverage, &members), all);
+    }
+}
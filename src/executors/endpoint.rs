@@ -19,10 +19,16 @@
 
 use crate::{
     error::LogicError,
-    executors::{settings::range_interaction, *},
+    executors::{
+        quests::quests_page,
+        ranking::ranking_page,
+        rolls::cleared,
+        settings::{cancel, confirm, range_interaction},
+        *,
+    },
     model::{
-        request::Request,
-        response::{Commands, ComponentMsg, Response},
+        request::{Request, Visibility},
+        response::{Commands, ComponentMsg, Invoker, Response, SlashCommand},
         translate::TranslateTo,
     },
 };
@@ -30,21 +36,32 @@ use itertools::Itertools;
 use roulette_macros::{bailout, pretty_info};
 
 #[tracing::instrument]
-pub fn interaction_endpoint(items: &[(String, Response)]) -> anyhow::Result<Request> {
+pub fn interaction_endpoint(
+    items: &[(String, Response)],
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
     tracing::debug!(got = ?items);
     match items {
         [first, options @ ..] => {
             if let Ok(command) = first.1.translate_to::<Commands>() {
                 let option_values = options.iter().map(|(_, v)| v).cloned().collect_vec();
                 match command {
-                    Commands::Settings => settings(&option_values),
+                    Commands::Settings => settings(&option_values, invoker),
                     Commands::Generate => generate(&option_values),
-                    Commands::Statistics => statistics(options),
+                    Commands::Statistics => statistics(options, invoker),
                     Commands::Version => Ok(version().unwrap()),
+                    Commands::Quests => quests(&option_values),
+                    Commands::Roulette => roulette(&option_values, invoker),
+                    Commands::Profile => profile(&option_values, invoker),
                 }
-            } else if let Ok(component) = first.1.translate_to::<ComponentMsg>() {
+            } else if let Ok(component) = first.translate_to::<ComponentMsg>() {
                 match component {
-                    ComponentMsg::Range(selected) => range_interaction(selected),
+                    ComponentMsg::Range(selected) => range_interaction(selected, invoker),
+                    ComponentMsg::Quests(rank) => quests_page(rank),
+                    ComponentMsg::Confirm(token) => confirm(token, invoker),
+                    ComponentMsg::Cancel(token) => cancel(token, invoker),
+                    ComponentMsg::Ranking { page, state } => ranking_page(page, &state, invoker),
+                    ComponentMsg::Cleared(roll) => cleared(roll, invoker),
                 }
             } else {
                 let expr = stringify!(first);
@@ -69,3 +86,22 @@ pub fn interaction_endpoint(items: &[(String, Response)]) -> anyhow::Result<Requ
         ),
     }
 }
+
+/// Visibility of the response to the application command in `items`.
+///
+/// It is decided before the executor runs, so that the interaction is deferred with it.
+pub fn response_visibility(items: &[(String, Response)]) -> Visibility {
+    let command = items
+        .first()
+        .and_then(|(_, first)| first.translate_to::<Commands>().ok());
+    let sub_command = items.get(1).and_then(|(_, second)| match second {
+        Response::SlashCommand(SlashCommand::SubCommand(name)) => Some(name.as_str()),
+        _ => None,
+    });
+    match (command, sub_command) {
+        (Some(Commands::Settings), _)
+        | (Some(Commands::Statistics), Some("help"))
+        | (Some(Commands::Profile), Some("set")) => Visibility::Ephemeral,
+        _ => Visibility::Public,
+    }
+}
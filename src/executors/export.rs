@@ -0,0 +1,292 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Export of logs and aggregates for `/statistics export`.
+
+use super::{
+    rolls,
+    statistics::{valid_period, valid_weapon},
+    time_zone::{local_datetime, time_zone, today},
+    utility::hunter_names,
+};
+use crate::{
+    data::Weapon,
+    error::{CommandError, QueryError},
+    global::CONN,
+    model::{
+        request::{Attachment, Message, Request},
+        response::{ExportFormat, Invoker},
+    },
+};
+use chrono::NaiveDateTime;
+use indoc::indoc;
+use itertools::Itertools;
+use roulette_macros::bailout;
+use serde_derive::Serialize;
+use serenity::model::{id::UserId, user::User};
+use std::collections::BTreeMap;
+use strum::IntoEnumIterator;
+
+/// Size limit of attachments for bots
+const ATTACHMENT_LIMIT: usize = 8 * 1024 * 1024;
+
+/// Roll of a hunter.
+#[derive(Debug, Serialize)]
+struct Log {
+    id: u64,
+    name: String,
+    weapon: String,
+    /// RFC 3339 in the time zone of the guild
+    generated_at: String,
+    roll_id: Option<i64>,
+    /// Quest ID of the roll, if a quest was rolled
+    quest: Option<String>,
+    /// Monster key of the roll, if a monster was rolled
+    monster: Option<String>,
+    cleared: bool,
+}
+
+/// Number of rolls of a hunter with a weapon.
+#[derive(Debug, Serialize)]
+struct Aggregate {
+    id: u64,
+    name: String,
+    weapon: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+struct Export<'a> {
+    logs: &'a [Log],
+    aggregates: &'a [Aggregate],
+}
+
+/// Exports logs of `user` (or all hunters) and their per-weapon aggregates.
+///
+/// Aggregates are counted from the exported logs, so they follow the same filters.
+pub(crate) fn export(
+    user: Option<User>,
+    format: ExportFormat,
+    weapon: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
+    let tz = time_zone(invoker.guild_id);
+    let weapons = match weapon {
+        Some(columns) => valid_weapon(&columns)?,
+        None => Weapon::iter()
+            .map(|weapon| weapon.to_string())
+            .collect_vec(),
+    };
+    let (since, until) = valid_period(since, until, today(tz))?;
+
+    let mut conditions = vec![format!(
+        "logs.weapon IN ({})",
+        weapons
+            .iter()
+            .map(|weapon| format!("'{weapon}'"))
+            .join(", ")
+    )];
+    if let Some(user) = &user {
+        conditions.push(format!("logs.id = {}", user.id.0));
+    }
+    if let Some(since) = since {
+        conditions.push(format!(
+            "date({}) >= '{since}'",
+            local_datetime("logs.generated_at", tz)
+        ));
+    }
+    if let Some(until) = until {
+        conditions.push(format!(
+            "date({}) <= '{until}'",
+            local_datetime("logs.generated_at", tz)
+        ));
+    }
+    let query = format!(
+        indoc! {r#"
+            SELECT
+                logs.id AS id,
+                logs.weapon AS weapon,
+                datetime(logs.generated_at, 'utc') AS generated_at,
+                logs.roll_id AS roll_id,
+                rolls.quest AS quest,
+                rolls.monster AS monster,
+                IFNULL(rolls.cleared, 0) AS cleared
+            FROM logs LEFT JOIN rolls ON rolls.id = logs.roll_id
+            WHERE {conditions}
+            ORDER BY logs.generated_at, logs.id
+        "#},
+        conditions = conditions.join(" AND "),
+    );
+
+    let logs = {
+        let conn = CONN.lock().unwrap();
+        rolls::ensure_tables(&conn)?;
+        let mut logs = vec![];
+        if let Err(err) = conn.iterate(&query, |pairs| {
+            if let [(_, Some(id)), (_, Some(weapon)), (_, generated_at), (_, roll_id), (_, quest), (_, monster), (_, cleared)] =
+                pairs
+            {
+                let generated_at = generated_at.unwrap_or_default();
+                if let Ok(id) = id.parse::<u64>() {
+                    logs.push(Log {
+                        id,
+                        // filled after the query
+                        name: String::new(),
+                        weapon: weapon.to_string(),
+                        generated_at: NaiveDateTime::parse_from_str(
+                            generated_at,
+                            "%Y-%m-%d %H:%M:%S",
+                        )
+                        .map(|utc| tz.to_rfc3339(&utc))
+                        .unwrap_or_else(|_| generated_at.to_string()),
+                        roll_id: roll_id.and_then(|roll_id| roll_id.parse::<i64>().ok()),
+                        quest: quest.map(str::to_string),
+                        monster: monster.map(str::to_string),
+                        cleared: *cleared != Some("0"),
+                    });
+                }
+            }
+            true
+        }) {
+            bailout!(
+                "query error",
+                QueryError::FailedToAggregate {
+                    raw: format!("{err}"),
+                    query
+                }
+            );
+        }
+        let ids = logs.iter().map(|log| UserId(log.id)).unique().collect_vec();
+        let names = hunter_names(&conn, &ids)?;
+        for log in &mut logs {
+            log.name = names
+                .get(&UserId(log.id))
+                .cloned()
+                .unwrap_or_else(|| log.id.to_string());
+        }
+        logs
+    };
+
+    let mut counts = BTreeMap::<(u64, &str), (&str, usize)>::new();
+    for log in &logs {
+        counts
+            .entry((log.id, log.weapon.as_str()))
+            .or_insert((log.name.as_str(), 0))
+            .1 += 1;
+    }
+    let aggregates = counts
+        .into_iter()
+        .map(|((id, weapon), (name, count))| Aggregate {
+            id,
+            name: name.to_string(),
+            weapon: weapon.to_string(),
+            count,
+        })
+        .collect_vec();
+
+    let stem = match &user {
+        Some(user) => format!("statistics-{}", user.id.0),
+        None => "statistics".to_string(),
+    };
+    let files = match format {
+        ExportFormat::Csv => vec![
+            Attachment {
+                filename: format!("{stem}-logs.csv"),
+                data: csv(
+                    &[
+                        "id",
+                        "name",
+                        "weapon",
+                        "generated_at",
+                        "roll_id",
+                        "quest",
+                        "monster",
+                        "cleared",
+                    ],
+                    logs.iter().map(|log| {
+                        vec![
+                            log.id.to_string(),
+                            log.name.clone(),
+                            log.weapon.clone(),
+                            log.generated_at.clone(),
+                            log.roll_id.map(|id| id.to_string()).unwrap_or_default(),
+                            log.quest.clone().unwrap_or_default(),
+                            log.monster.clone().unwrap_or_default(),
+                            log.cleared.to_string(),
+                        ]
+                    }),
+                ),
+            },
+            Attachment {
+                filename: format!("{stem}-aggregates.csv"),
+                data: csv(
+                    &["id", "name", "weapon", "count"],
+                    aggregates.iter().map(|aggregate| {
+                        vec![
+                            aggregate.id.to_string(),
+                            aggregate.name.clone(),
+                            aggregate.weapon.clone(),
+                            aggregate.count.to_string(),
+                        ]
+                    }),
+                ),
+            },
+        ],
+        ExportFormat::Json => vec![Attachment {
+            filename: format!("{stem}.json"),
+            data: serde_json::to_vec_pretty(&Export {
+                logs: &logs,
+                aggregates: &aggregates,
+            })?,
+        }],
+    };
+    if files.iter().map(|file| file.data.len()).sum::<usize>() > ATTACHMENT_LIMIT {
+        bailout!(
+            "Too large",
+            CommandError::InvalidArgument {
+                arg: "the export exceeds 8 MiB, narrow down the period".to_string(),
+            }
+        );
+    }
+    Ok(Request::Attachments {
+        message: Message::String(format!(
+            "exported {} log(s) of {}",
+            logs.len(),
+            user.map_or_else(|| "all hunters".to_string(), |user| user.name)
+        )),
+        files,
+    })
+}
+
+/// Writes rows as CSV (RFC 4180), quoting fields only if needed.
+fn csv(header: &[&str], rows: impl Iterator<Item = Vec<String>>) -> Vec<u8> {
+    let escape = |field: &str| {
+        if field.contains(&[',', '"', '\n', '\r'][..]) {
+            format!("\"{}\"", field.replace('"', "\"\""))
+        } else {
+            field.to_string()
+        }
+    };
+    std::iter::once(header.iter().map(|field| escape(field)).join(","))
+        .chain(rows.map(|row| row.iter().map(|field| escape(field)).join(",")))
+        .map(|line| line + "\r\n")
+        .collect::<String>()
+        .into_bytes()
+}
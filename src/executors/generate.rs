@@ -19,7 +19,7 @@
 
 #![allow(clippy::nonstandard_macro_braces)]
 use anyhow::Context;
-use itertools::{zip, Itertools};
+use itertools::Itertools;
 use rand::{
     distributions::{Distribution, Uniform},
     seq::{IteratorRandom, SliceRandom},
@@ -29,23 +29,30 @@ use serenity::{builder::CreateEmbed, utils::Colour};
 use strum::IntoEnumIterator;
 
 use crate::{
-    data::{Monster, Order, TargetRank, Weapon},
+    data::{Monster, Order, QuestID, TargetRank, Weapon},
     error::{CommandError, QueryError},
-    executors::utility::JobStatus,
+    executors::{
+        coverage::{coverage, uncovered_monsters, uncovered_weapons},
+        profile::profiles,
+        recency::{avoid_all, recent, Relaxation},
+        rolls::{self, Target},
+        utility::{hunter_names, JobStatus, TIME_LIMIT},
+        weights::draw,
+    },
     global::{CONFIG, CONN, OBJECTIVES, QUESTS},
     model::{
-        request::{Message, Request},
-        response::{Choices, Response},
+        request::{Buttons, Component, Message, Request},
+        response::{Choices, CoverageBasis, Response},
         translate::TranslateTo,
     },
 };
 use roulette_macros::bailout;
-use serenity::model::user::User;
+use serenity::model::id::UserId;
 use sqlite::Connection;
 use std::{
+    collections::HashMap,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::Duration,
 };
 use thiserror::Error;
 
@@ -54,35 +61,114 @@ enum GenerateType {
     Monster,
 }
 
+/// # generate command
+///
+/// ## options
+/// - type: quest/monster
+/// - coverage (optional): prioritizes cells that are not covered yet (see [coverage])
+///
+/// Recent weapons and targets are avoided within the windows of `/settings recency` (see [avoid_all]),
+/// and the rest are drawn in proportion to `/settings weight` (see [draw]).
 pub fn generate(items: &[Response]) -> anyhow::Result<Request> {
     match items {
-        [opt] => match opt.clone().translate_to::<Choices>()? {
-            Choices::Quest => generate_impl(GenerateType::Quest),
-            Choices::Monster => generate_impl(GenerateType::Monster),
-            _ => Err(anyhow::anyhow!("unknown command option: {:?}", opt)),
-        },
+        [opt, rest @ ..] => {
+            let coverage_mode = rest
+                .iter()
+                .any(|item| item.translate_to::<bool>().unwrap_or_default());
+            match opt.clone().translate_to::<Choices>()? {
+                Choices::Quest => generate_impl(GenerateType::Quest, coverage_mode),
+                Choices::Monster => generate_impl(GenerateType::Monster, coverage_mode),
+                _ => Err(anyhow::anyhow!("unknown command option: {:?}", opt)),
+            }
+        }
         _ => Err(anyhow::anyhow!("invalid : {:?}", items)),
     }
 }
 
-fn generate_impl(gen_type: GenerateType) -> anyhow::Result<Request> {
+fn generate_impl(gen_type: GenerateType, coverage_mode: bool) -> anyhow::Result<Request> {
     let mut rng = thread_rng();
     let config = CONFIG.lock().unwrap();
-    let members: Vec<_> = config.members.iter().choose_multiple(&mut rng, 4);
-    let weapons: Vec<Weapon> = Weapon::iter().collect();
+    let members: Vec<_> = config.members.iter().copied().choose_multiple(&mut rng, 4);
+    let (names, profiles, cleared, recent) = {
+        let conn = CONN.lock().unwrap();
+        (
+            hunter_names(&conn, &members)?,
+            profiles(&conn, &members)?,
+            if coverage_mode {
+                coverage(&conn, &members, CoverageBasis::Cleared)?
+            } else {
+                HashMap::new()
+            },
+            recent(&conn, &members, config.settings.recency)?,
+        )
+    };
+    let weights = &config.settings.weights;
+    let name = |id: &UserId| names.get(id).cloned().unwrap_or_else(|| id.to_string());
     let order_num = 5 - members.len();
     let orders = Order::iter()
         .choose_multiple(&mut rng, order_num)
         .into_iter()
         .map(|order| format!("* {order}"))
         .join("\n");
-    let regulations = zip(
-        members.into_iter(),
-        Uniform::new(0, weapons.len())
-            .sample_iter(&mut rng)
-            .map(|idx| weapons[idx as usize]),
-    )
-    .collect_vec();
+    let weapons = members
+        .iter()
+        .map(|member| {
+            let candidates = if coverage_mode {
+                uncovered_weapons(cleared.get(member))
+            } else {
+                Weapon::iter().collect()
+            };
+            (*member, candidates)
+        })
+        .collect_vec();
+    let targets = match gen_type {
+        GenerateType::Quest => {
+            let TargetRank { ref ranks } = config.settings.ranks;
+            // Every selected member must be able to join the quest.
+            let ranks = ranks
+                .iter()
+                .filter(|rank| profiles.values().all(|profile| profile.can_access(**rank)))
+                .filter(|rank| {
+                    QUESTS
+                        .get(**rank)
+                        .map_or(false, |quests| !quests.is_empty())
+                })
+                .collect_vec();
+            if ranks.is_empty() {
+                bailout!(
+                    "No accessible rank",
+                    CommandError::NoCandidates {
+                        reason: "no target quest rank is accessible to all selected members"
+                            .to_string(),
+                    }
+                );
+            }
+            let rank = ranks
+                .into_iter()
+                .choose(&mut rng)
+                .with_context(|| anyhow::anyhow!("failed to choose."))?;
+            (0..QUESTS[*rank].len())
+                .map(|number| Target::Quest(QuestID(*rank as u32, number as u32)))
+                .collect_vec()
+        }
+        GenerateType::Monster => if coverage_mode {
+            uncovered_monsters(&cleared, &members)
+        } else {
+            Monster::iter().collect()
+        }
+        .into_iter()
+        .map(Target::Monster)
+        .collect_vec(),
+    };
+    let (targets, weapons, relaxations) = avoid_all(&recent, targets, weapons);
+    let regulations = weapons
+        .iter()
+        .map(|(member, candidates)| {
+            draw(candidates, |weapon| weights.weapon(weapon), &mut rng)
+                .map(|weapon| (*member, weapon))
+                .with_context(|| anyhow::anyhow!("failed to choose."))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
     let general_objectives: Vec<Order> = Order::iter().collect();
     let objectives = regulations
         .iter()
@@ -106,18 +192,21 @@ fn generate_impl(gen_type: GenerateType) -> anyhow::Result<Request> {
         })
         .collect::<anyhow::Result<Vec<_>>>()?
         .join("\n");
-    let response = match gen_type {
-        GenerateType::Quest => {
-            let TargetRank { ref ranks } = config.settings.ranks;
-            let quest = ranks
-                .iter()
-                .map(|idx| &QUESTS[*idx])
-                .collect_vec()
-                .choose(&mut rng)
-                .map(|quests| quests.choose(&mut rng))
-                .flatten()
+    let target = draw(
+        &targets,
+        |target| match target {
+            Target::Quest(id) => weights.quest(id),
+            Target::Monster(monster) => weights.monster(monster),
+        },
+        &mut rng,
+    )
+    .with_context(|| anyhow::anyhow!("failed to choose."))?;
+    let mut embed = CreateEmbed::default();
+    match target {
+        Target::Quest(id) => {
+            let quest = id
+                .quest()
                 .with_context(|| anyhow::anyhow!("failed to choose."))?;
-            let mut embed = CreateEmbed::default();
             embed
                 .colour(Colour::BLUE)
                 .title(quest.title())
@@ -126,15 +215,10 @@ fn generate_impl(gen_type: GenerateType) -> anyhow::Result<Request> {
                 .fields(
                     regulations
                         .iter()
-                        .map(|(user, weapon)| (&user.name, weapon.ja(), true)),
+                        .map(|(user, weapon)| (name(user), weapon.ja(), true)),
                 );
-            Ok(Request::Message(Message::Embed(embed)))
         }
-        GenerateType::Monster => {
-            let monster = Monster::iter()
-                .choose(&mut rng)
-                .with_context(|| anyhow::anyhow!("failed to choose."))?;
-            let mut embed = CreateEmbed::default();
+        Target::Monster(monster) => {
             embed
                 .colour(Colour::BLUE)
                 .title(monster.ja())
@@ -142,17 +226,38 @@ fn generate_impl(gen_type: GenerateType) -> anyhow::Result<Request> {
                 .fields(
                     regulations
                         .iter()
-                        .map(|(user, weapon)| (&user.name, weapon.ja(), true)),
+                        .map(|(user, weapon)| (name(user), weapon.ja(), true)),
                 );
-            Ok(Request::Message(Message::Embed(embed)))
         }
-    };
-    let regulations = regulations
-        .into_iter()
-        .map(|(user, weapon)| (user.clone(), weapon))
-        .collect_vec();
-    store(regulations)?;
-    response
+    }
+    let roll = store(regulations, target)?;
+    let mut footer = vec![format!("roll #{roll}")];
+    if coverage_mode {
+        footer.push("coverage mode".to_string());
+    }
+    if !relaxations.is_empty() {
+        let relaxed = relaxations
+            .iter()
+            .map(|relaxation| match relaxation {
+                Relaxation::Targets { kept, window } => {
+                    format!("last {kept}/{window} targets")
+                }
+                Relaxation::Weapons {
+                    member,
+                    kept,
+                    window,
+                } => format!("last {kept}/{window} weapons of {}", name(member)),
+            })
+            .join(", ");
+        footer.push(format!("repeats allowed, avoided only {relaxed}"));
+    }
+    embed.footer(|f| f.text(footer.join(" | ")));
+    Ok(Request::Interactive {
+        message: Message::Embed(embed),
+        components: vec![Component::Buttons(Buttons::new(&[rolls::clear_button(
+            roll, None,
+        )]))],
+    })
 }
 
 enum QueryKind {
@@ -162,8 +267,8 @@ enum QueryKind {
 
 #[derive(Debug, Error)]
 enum Query {
-    #[error("INSERT INTO logs (id, weapon) VALUES ({id:?}, {weapon:?})")]
-    InsertIntoLogs { id: u64, weapon: String },
+    #[error("INSERT INTO logs (id, weapon, roll_id) VALUES ({id:?}, {weapon:?}, {roll:?})")]
+    InsertIntoLogs { id: u64, weapon: String, roll: i64 },
     #[error(
         r#"
         INSERT INTO statistics (id, {weapon:?}) VALUES ({id:?}, 1)
@@ -175,19 +280,33 @@ enum Query {
     UpsetStatistics { id: u64, weapon: String },
 }
 
-fn store(data: Vec<(User, Weapon)>) -> anyhow::Result<()> {
+/// Stores the roll and returns its id.
+fn store(data: Vec<(UserId, Weapon)>, target: Target) -> anyhow::Result<i64> {
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conn = Arc::clone(&*CONN);
 
-    let handle = thread::spawn(move || -> anyhow::Result<()> {
+    let handle = thread::spawn(move || -> anyhow::Result<i64> {
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut conn) = conn.try_lock() {
                 let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    return Err(anyhow::anyhow!("cancelled after the time limit"));
+                }
+
+                // First, we should start a roll to link logs to.
+                let roll = match rolls::start(conn, target) {
+                    Ok(roll) => roll,
+                    Err(err) => {
+                        *status = JobStatus::ExitFailure;
+                        cvar.notify_one();
+                        return Err(err);
+                    }
+                };
 
-                // First, we should insert results into logs.
-                if let Err((query, err)) = execute(QueryKind::InsertIntoLogs, conn, &data) {
+                // Second, we should insert results into logs.
+                if let Err((query, err)) = execute(QueryKind::InsertIntoLogs, conn, roll, &data) {
                     *status = JobStatus::ExitFailure;
                     cvar.notify_one();
                     return Err(QueryError::FailedToStore {
@@ -197,8 +316,8 @@ fn store(data: Vec<(User, Weapon)>) -> anyhow::Result<()> {
                     .with_context(|| anyhow::anyhow!("Query failed."));
                 }
 
-                // Second, we should upset statistics.
-                if let Err((query, err)) = execute(QueryKind::UpsetStatistics, conn, &data) {
+                // Third, we should upset statistics.
+                if let Err((query, err)) = execute(QueryKind::UpsetStatistics, conn, roll, &data) {
                     *status = JobStatus::ExitFailure;
                     cvar.notify_one();
                     return Err(QueryError::FailedToStore {
@@ -210,18 +329,16 @@ fn store(data: Vec<(User, Weapon)>) -> anyhow::Result<()> {
 
                 *status = JobStatus::ExitSuccess;
                 cvar.notify_one();
-                break Ok(());
+                break Ok(roll);
             }
         }
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(
-            lock.lock().unwrap(),
-            Duration::from_millis(1000),
-            |status| *status == JobStatus::Pending,
-        )
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
+            *status == JobStatus::Pending
+        })
         .unwrap();
     loop {
         if result.0.ne(&JobStatus::Pending) {
@@ -229,11 +346,12 @@ fn store(data: Vec<(User, Weapon)>) -> anyhow::Result<()> {
                 .join()
                 .expect("Couldn't join on the associated thread");
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "generate".to_string(),
-                    wait_for: Duration::from_millis(1000),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
@@ -243,21 +361,23 @@ fn store(data: Vec<(User, Weapon)>) -> anyhow::Result<()> {
 fn execute(
     kind: QueryKind,
     conn: &mut Connection,
-    data: &[(User, Weapon)],
+    roll: i64,
+    data: &[(UserId, Weapon)],
 ) -> anyhow::Result<(), (String, sqlite::Error)> {
     for (user, weapon) in data {
         match kind {
             QueryKind::InsertIntoLogs => {
                 let query = Query::InsertIntoLogs {
-                    id: user.id.0,
+                    id: user.0,
                     weapon: weapon.to_string(),
+                    roll,
                 };
                 conn.execute(format!("{query}"))
                     .map_err(|err| (format!("{query}"), err))?;
             }
             QueryKind::UpsetStatistics => {
                 let query = Query::UpsetStatistics {
-                    id: user.id.0,
+                    id: user.0,
                     weapon: weapon.to_string(),
                 };
                 conn.execute(format!("{query}"))
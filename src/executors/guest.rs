@@ -0,0 +1,363 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Guest members who are not Discord users.
+//!
+//! Guests are stored in the hunters table with synthetic IDs below [GUEST_ID_LIMIT],
+//! so that they take part in generation and statistics like Discord users.
+
+use crate::{
+    data::Weapon,
+    error::CommandError,
+    executors::{
+        profile,
+        utility::{failed_to_aggregate, failed_to_store},
+    },
+    global::{sync_all, CONFIG, CONN},
+    model::{
+        request::{Message, Request},
+        response::GuestOptions,
+    },
+};
+use itertools::Itertools;
+use roulette_macros::bailout;
+use serenity::model::{id::UserId, user::User};
+use sqlite::{Connection, State};
+use strum::IntoEnumIterator;
+use thiserror::Error;
+
+/// Discord IDs are timestamps shifted by 22 bits, so no Discord user has an ID below this.
+pub(crate) const GUEST_ID_LIMIT: u64 = 1 << 22;
+
+/// Returns true if `id` is a synthetic ID of a guest.
+pub(crate) fn is_guest(id: &UserId) -> bool {
+    id.0 < GUEST_ID_LIMIT
+}
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error("SELECT id FROM hunters WHERE id < {limit} AND name = ?")]
+    FindGuest { limit: u64 },
+    #[error("SELECT IFNULL(MAX(id), 0) + 1 FROM hunters WHERE id < {limit}")]
+    NextGuestId { limit: u64 },
+    #[error("INSERT INTO hunters (id, name) VALUES (?, ?)")]
+    InsertGuest,
+    #[error(
+        r#"
+        INSERT INTO hunters (id, name) VALUES (?, ?)
+            ON CONFLICT (id)
+                DO UPDATE SET
+                    name = excluded.name,
+                    updated_at = datetime('now', 'localtime')
+    "#
+    )]
+    UpsetMember,
+    #[error(
+        r#"
+        INSERT INTO statistics (id, {columns})
+            SELECT {user}, {columns} FROM statistics WHERE id = {guest}
+            ON CONFLICT (id)
+                DO UPDATE SET {updates}
+    "#
+    )]
+    MergeStatistics {
+        guest: u64,
+        user: u64,
+        columns: String,
+        updates: String,
+    },
+    #[error("DELETE FROM statistics WHERE id = {guest}")]
+    DeleteStatistics { guest: u64 },
+    #[error("UPDATE logs SET id = {user} WHERE id = {guest}")]
+    MoveLogs { guest: u64, user: u64 },
+    #[error(
+        r#"
+        UPDATE hunters SET
+            hr = COALESCE(
+                MAX(hr, (SELECT hr FROM hunters WHERE id = {guest})),
+                hr,
+                (SELECT hr FROM hunters WHERE id = {guest})
+            ),
+            star = COALESCE(
+                MAX(star, (SELECT star FROM hunters WHERE id = {guest})),
+                star,
+                (SELECT star FROM hunters WHERE id = {guest})
+            )
+            WHERE id = {user}
+    "#
+    )]
+    MergeProfile { guest: u64, user: u64 },
+    #[error("DELETE FROM hunters WHERE id = {guest}")]
+    DeleteGuest { guest: u64 },
+}
+
+/// Returns the ID of the guest named `name`.
+fn find(conn: &Connection, name: &str) -> anyhow::Result<Option<UserId>> {
+    let query = Query::FindGuest {
+        limit: GUEST_ID_LIMIT,
+    };
+    (|| -> sqlite::Result<_> {
+        let mut statement = conn.prepare(format!("{query}"))?;
+        statement.bind(1, name)?;
+        Ok(match statement.next()? {
+            State::Row => Some(UserId(statement.read::<i64>(0)? as u64)),
+            State::Done => None,
+        })
+    })()
+    .map_err(|err| failed_to_aggregate(&query, err))
+}
+
+/// Returns the ID of the guest named `name`, registering a new guest if needed.
+fn find_or_create(conn: &Connection, name: &str) -> anyhow::Result<UserId> {
+    if let Some(id) = find(conn, name)? {
+        return Ok(id);
+    }
+    let query = Query::NextGuestId {
+        limit: GUEST_ID_LIMIT,
+    };
+    let id = (|| {
+        let mut statement = conn.prepare(format!("{query}"))?;
+        statement.next()?;
+        statement.read::<i64>(0)
+    })()
+    .map_err(|err| failed_to_store(&query, err))? as u64;
+    if id >= GUEST_ID_LIMIT {
+        bailout!(
+            "Too many guests",
+            CommandError::InvalidArgument {
+                arg: name.to_string(),
+            }
+        );
+    }
+    (|| {
+        let mut statement = conn.prepare(format!("{}", Query::InsertGuest))?;
+        statement.bind(1, id as i64)?;
+        statement.bind(2, name)?;
+        statement.next().map(|_| ())
+    })()
+    .map_err(|err| failed_to_store(&Query::InsertGuest, err))?;
+    Ok(UserId(id))
+}
+
+/// Moves the logs, statistics and profile of `guest` to `user`, and deletes the guest.
+///
+/// The merged profile keeps the higher HR and star of the two.
+fn merge(conn: &Connection, guest: UserId, user: &User) -> anyhow::Result<()> {
+    profile::ensure_columns(conn)?;
+    let columns = Weapon::iter().map(|weapon| format!("{:?}", weapon.to_string()));
+    let queries = [
+        Query::MergeStatistics {
+            guest: guest.0,
+            user: user.id.0,
+            columns: columns.clone().join(", "),
+            updates: columns
+                .map(|column| {
+                    format!("{column} = IFNULL({column}, 0) + IFNULL(excluded.{column}, 0)")
+                })
+                .join(", "),
+        },
+        Query::DeleteStatistics { guest: guest.0 },
+        Query::MoveLogs {
+            guest: guest.0,
+            user: user.id.0,
+        },
+        Query::MergeProfile {
+            guest: guest.0,
+            user: user.id.0,
+        },
+        Query::DeleteGuest { guest: guest.0 },
+    ];
+    conn.execute("BEGIN")
+        .map_err(|err| anyhow::anyhow!("failed to begin a transaction: {}", err))?;
+    let result = (|| {
+        // The user needs a row to merge the profile into.
+        (|| {
+            let mut statement = conn.prepare(format!("{}", Query::UpsetMember))?;
+            statement.bind(1, user.id.0 as i64)?;
+            statement.bind(2, user.name.as_str())?;
+            statement.next().map(|_| ())
+        })()
+        .map_err(|err| failed_to_store(&Query::UpsetMember, err))?;
+        for query in queries.iter() {
+            conn.execute(format!("{query}"))
+                .map_err(|err| failed_to_store(query, err))?;
+        }
+        Ok(())
+    })();
+    conn.execute(if result.is_ok() { "COMMIT" } else { "ROLLBACK" })
+        .map_err(|err| anyhow::anyhow!("failed to end the transaction: {}", err))?;
+    result
+}
+
+/// Adds, removes or merges the guest named `name`.
+pub(crate) fn guest(
+    option: GuestOptions,
+    name: String,
+    user: Option<User>,
+) -> anyhow::Result<Request> {
+    let name = name.trim().to_string();
+    if name.is_empty() {
+        bailout!(
+            "Empty name",
+            CommandError::InvalidArgument {
+                arg: "guest name".to_string(),
+            }
+        );
+    }
+    let message = match option {
+        GuestOptions::Add => {
+            let id = find_or_create(&CONN.lock().unwrap(), &name)?;
+            CONFIG.lock().unwrap().members.insert(id);
+            format!("guest {name} joined.")
+        }
+        GuestOptions::Remove => {
+            let id = find(&CONN.lock().unwrap(), &name)?;
+            match id {
+                Some(id) => CONFIG.lock().unwrap().members.remove(&id),
+                None => bailout!("No such guest", CommandError::InvalidArgument { arg: name }),
+            };
+            format!("guest {name} left.")
+        }
+        GuestOptions::Merge => {
+            let user = match user {
+                Some(user) => user,
+                None => bailout!(
+                    "No user to merge into",
+                    CommandError::InvalidArgument {
+                        arg: "user".to_string(),
+                    }
+                ),
+            };
+            let id = {
+                let conn = CONN.lock().unwrap();
+                let id = find(&conn, &name)?;
+                match id {
+                    Some(id) => {
+                        merge(&conn, id, &user)?;
+                        id
+                    }
+                    None => bailout!("No such guest", CommandError::InvalidArgument { arg: name }),
+                }
+            };
+            {
+                let mut config = CONFIG.lock().unwrap();
+                if config.members.remove(&id) {
+                    config.members.insert(user.id);
+                }
+            }
+            format!("guest {name} has been merged into {}.", user.name)
+        }
+    };
+    sync_all().map_err(|err| {
+        anyhow::Error::from(CommandError::FailedToSync {
+            command: "settings guest".to_string(),
+            io_error: err,
+        })
+        .context("sync_all failed.")
+    })?;
+    Ok(Request::Message(Message::String(message)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::executors::profile::profiles;
+    use indoc::indoc;
+
+    fn conn() -> Connection {
+        let conn = sqlite::open(":memory:").unwrap();
+        conn.execute(format!(
+            indoc! {r#"
+                CREATE TABLE hunters (
+                    id         INTEGER PRIMARY KEY,
+                    name       TEXT NOT NULL,
+                    updated_at TEXT
+                );
+                CREATE TABLE statistics (id INTEGER PRIMARY KEY, {columns});
+                CREATE TABLE logs (id INTEGER NOT NULL, weapon TEXT NOT NULL);
+            "#},
+            columns = Weapon::iter()
+                .map(|weapon| format!("{:?} INTEGER", weapon.to_string()))
+                .join(", "),
+        ))
+        .unwrap();
+        profile::ensure_columns(&conn).unwrap();
+        conn
+    }
+
+    fn value(conn: &Connection, query: String) -> Option<String> {
+        let mut value = None;
+        conn.iterate(query, |pairs| {
+            value = pairs[0].1.map(str::to_string);
+            true
+        })
+        .unwrap();
+        value
+    }
+
+    #[test]
+    fn merge_test() {
+        let conn = conn();
+        let guest = find_or_create(&conn, "guest").unwrap();
+        assert!(is_guest(&guest));
+        let mut user = User::default();
+        user.id = UserId(GUEST_ID_LIMIT << 1);
+        user.name = "hunter".to_string();
+        let bow = Weapon::Bow.to_string();
+        conn.execute(format!(
+            indoc! {r#"
+                UPDATE hunters SET hr = 50, star = 7 WHERE id = {guest};
+                INSERT INTO hunters (id, name, hr) VALUES ({user}, 'hunter', 100);
+                INSERT INTO statistics (id, {bow:?}) VALUES ({guest}, 2), ({user}, 1);
+                INSERT INTO logs (id, weapon) VALUES ({guest}, '{bow}');
+            "#},
+            guest = guest.0,
+            user = user.id.0,
+            bow = bow,
+        ))
+        .unwrap();
+
+        merge(&conn, guest, &user).unwrap();
+        assert_eq!(find(&conn, "guest").unwrap(), None);
+
+        // The user keeps the higher HR, and takes the star that only the guest had.
+        let profiles = profiles(&conn, &[guest, user.id]).unwrap();
+        assert!(!profiles.contains_key(&guest));
+        let profile = profiles[&user.id];
+        assert_eq!((profile.hr, profile.star), (100, 7));
+
+        let count = |table: &str, id: UserId| {
+            value(
+                &conn,
+                format!("SELECT COUNT(*) FROM {table} WHERE id = {}", id.0),
+            )
+        };
+        assert_eq!(count("statistics", guest).as_deref(), Some("0"));
+        assert_eq!(count("logs", guest).as_deref(), Some("0"));
+        assert_eq!(count("logs", user.id).as_deref(), Some("1"));
+        assert_eq!(
+            value(
+                &conn,
+                format!("SELECT {bow:?} FROM statistics WHERE id = {}", user.id.0)
+            )
+            .as_deref(),
+            Some("3")
+        );
+    }
+}
@@ -0,0 +1,372 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+//! Audit log of configuration changes.
+
+use crate::{
+    data::{config_diff, Config, Roles, Settings},
+    error::CommandError,
+    executors::utility::{failed_to_aggregate, failed_to_store},
+    global::{sync_all, CONFIG, CONN},
+    model::response::Invoker,
+};
+use anyhow::Context;
+use roulette_macros::bailout;
+use serde_derive::{Deserialize, Serialize};
+use serenity::model::{id::UserId, user::User};
+use std::collections::{BTreeMap, HashSet};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error(
+        r#"
+        CREATE TABLE IF NOT EXISTS settings_history (
+            id         INTEGER PRIMARY KEY AUTOINCREMENT,
+            user_id    INTEGER NOT NULL,
+            user_name  TEXT    NOT NULL,
+            command    TEXT    NOT NULL,
+            before     TEXT    NOT NULL,
+            after      TEXT    NOT NULL,
+            diff       TEXT    NOT NULL,
+            undone     INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT    NOT NULL DEFAULT (datetime('now', 'localtime'))
+        )
+    "#
+    )]
+    CreateTable,
+    #[error(
+        r#"
+        INSERT INTO settings_history (user_id, user_name, command, before, after, diff)
+            VALUES (?, ?, ?, ?, ?, ?)
+    "#
+    )]
+    Insert,
+    #[error(
+        r#"
+        SELECT id, user_name, command, diff, undone, datetime(created_at, 'utc') AS created_at
+            FROM settings_history
+            ORDER BY id DESC LIMIT {limit}
+    "#
+    )]
+    Recent { limit: usize },
+    #[error(
+        r#"
+        SELECT id, before, after FROM settings_history
+            WHERE undone = 0 ORDER BY id DESC LIMIT 1
+    "#
+    )]
+    Last,
+    #[error("UPDATE settings_history SET undone = 1 WHERE id = {id}")]
+    MarkUndone { id: i64 },
+}
+
+/// Part of [Config] that can be changed by settings commands.
+#[derive(Serialize)]
+struct SnapshotRef<'a> {
+    members: &'a HashSet<UserId>,
+    settings: &'a Settings,
+    roles: &'a Roles,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    time_zones: &'a BTreeMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct Snapshot {
+    members: HashSet<UserId>,
+    settings: Settings,
+    /// Missing in snapshots recorded before roles were, which leave roles as they are
+    roles: Option<Roles>,
+    /// Missing in snapshots recorded before time zones were introduced
+    #[serde(default)]
+    time_zones: BTreeMap<String, String>,
+}
+
+/// Entry of the audit log.
+#[derive(Debug)]
+pub(crate) struct Change {
+    pub id: i64,
+    pub user: String,
+    pub command: String,
+    pub diff: String,
+    pub undone: bool,
+    /// UTC, `YYYY-MM-DD HH:MM:SS`
+    pub created_at: String,
+}
+
+/// Returns current members, settings, roles and time zones as TOML.
+pub(crate) fn snapshot() -> String {
+    snapshot_of(&CONFIG.lock().unwrap())
+}
+
+fn snapshot_of(config: &Config) -> String {
+    toml::to_string_pretty(&SnapshotRef {
+        members: &config.members,
+        settings: &config.settings,
+        roles: &config.roles,
+        time_zones: &config.time_zones,
+    })
+    .unwrap()
+}
+
+fn column(pairs: &[(&str, Option<&str>)], name: &str) -> String {
+    pairs
+        .iter()
+        .find(|(key, _)| *key == name)
+        .and_then(|(_, value)| *value)
+        .unwrap_or_default()
+        .to_string()
+}
+
+fn create_table(conn: &sqlite::Connection) -> anyhow::Result<()> {
+    conn.execute(format!("{}", Query::CreateTable))
+        .map_err(|err| failed_to_store(Query::CreateTable, err))
+}
+
+/// Records the change from `before` to the current configuration.
+/// Nothing is recorded if the configuration is unchanged.
+pub(crate) fn record(invoker: &Invoker, command: &str, before: &str) -> anyhow::Result<()> {
+    let after = snapshot();
+    insert(
+        &CONN.lock().unwrap(),
+        &invoker.user,
+        command,
+        before,
+        &after,
+    )
+    .map(|_| ())
+}
+
+/// Inserts the change from `before` to `after` unless they are the same.
+/// Returns whether the change has been inserted.
+fn insert(
+    conn: &sqlite::Connection,
+    user: &User,
+    command: &str,
+    before: &str,
+    after: &str,
+) -> anyhow::Result<bool> {
+    let diff = config_diff(before, after);
+    if diff.is_empty() {
+        return Ok(false);
+    }
+    create_table(conn)?;
+    (|| {
+        let mut statement = conn.prepare(format!("{}", Query::Insert))?;
+        statement.bind(1, user.id.0 as i64)?;
+        statement.bind(2, user.name.as_str())?;
+        statement.bind(3, command)?;
+        statement.bind(4, before)?;
+        statement.bind(5, after)?;
+        statement.bind(6, diff.as_str())?;
+        statement.next().map(|_| ())
+    })()
+    .map_err(|err| failed_to_store(Query::Insert, err))?;
+    Ok(true)
+}
+
+/// Returns the `limit` most recent changes, newest first.
+pub(crate) fn recent(limit: usize) -> anyhow::Result<Vec<Change>> {
+    recent_in(&CONN.lock().unwrap(), limit)
+}
+
+fn recent_in(conn: &sqlite::Connection, limit: usize) -> anyhow::Result<Vec<Change>> {
+    create_table(conn)?;
+    let mut changes = vec![];
+    conn.iterate(format!("{}", Query::Recent { limit }), |pairs| {
+        changes.push(Change {
+            id: column(pairs, "id").parse().unwrap_or_default(),
+            user: column(pairs, "user_name"),
+            command: column(pairs, "command"),
+            diff: column(pairs, "diff"),
+            undone: column(pairs, "undone") != "0",
+            created_at: column(pairs, "created_at"),
+        });
+        true
+    })
+    .map_err(|err| failed_to_aggregate(Query::Recent { limit }, err))?;
+    Ok(changes)
+}
+
+/// Reverts the last change that has not been undone yet.
+/// Returns the id of the change and the diff of the reversion.
+pub(crate) fn undo() -> anyhow::Result<(i64, String)> {
+    let last = last(&CONN.lock().unwrap())?;
+    let (id, diff) = revert(&mut CONFIG.lock().unwrap(), last)?;
+    sync_all().map_err(|err| {
+        anyhow::Error::from(CommandError::FailedToSync {
+            command: "settings undo".to_string(),
+            io_error: err,
+        })
+        .context("sync_all failed.")
+    })?;
+    mark_undone(&CONN.lock().unwrap(), id)?;
+    Ok((id, diff))
+}
+
+/// Returns the id, `before` and `after` of the last change that has not been undone yet.
+fn last(conn: &sqlite::Connection) -> anyhow::Result<Option<(i64, String, String)>> {
+    create_table(conn)?;
+    let mut last = None;
+    conn.iterate(format!("{}", Query::Last), |pairs| {
+        last = Some((
+            column(pairs, "id").parse::<i64>().unwrap_or_default(),
+            column(pairs, "before"),
+            column(pairs, "after"),
+        ));
+        true
+    })
+    .map_err(|err| failed_to_aggregate(Query::Last, err))?;
+    Ok(last)
+}
+
+/// Restores `config` to `before` of the `last` change.
+///
+/// Fails if `config` has been changed since `after` of the change.
+/// Returns the id of the change and the diff of the reversion.
+fn revert(
+    config: &mut Config,
+    last: Option<(i64, String, String)>,
+) -> anyhow::Result<(i64, String)> {
+    let (id, before, after) = match last {
+        Some(last) => last,
+        None => bailout!(
+            "Nothing to undo",
+            CommandError::CannotUndo {
+                reason: "no change has been recorded".to_string(),
+            }
+        ),
+    };
+    if !config_diff(&snapshot_of(config), &after).is_empty() {
+        bailout!(
+            "Conflicted",
+            CommandError::CannotUndo {
+                reason: format!("the configuration has been changed since change #{id}"),
+            }
+        );
+    }
+    let snapshot: Snapshot = toml::from_str(&before)
+        .with_context(|| anyhow::anyhow!("broken snapshot of change #{}", id))?;
+    config.members = snapshot.members;
+    config.settings = snapshot.settings;
+    if let Some(roles) = snapshot.roles {
+        config.roles = roles;
+    }
+    config.time_zones = snapshot.time_zones;
+    Ok((id, config_diff(&after, &before)))
+}
+
+fn mark_undone(conn: &sqlite::Connection, id: i64) -> anyhow::Result<()> {
+    conn.execute(format!("{}", Query::MarkUndone { id }))
+        .map_err(|err| failed_to_store(Query::MarkUndone { id }, err))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serenity::model::id::RoleId;
+
+    fn config() -> Config {
+        toml::from_str(indoc::indoc! {r#"
+            members = [1, 2]
+
+            [roles]
+
+            [settings.ranks]
+            ranks = [5, 6]
+
+            [settings.target]
+            quest = []
+            monster = []
+            weapon = []
+
+            [settings.excluded]
+            quest = []
+            monster = []
+            weapon = ["Bow"]
+        "#})
+        .unwrap()
+    }
+
+    #[test]
+    fn record_and_undo_test() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let user = User::default();
+        let mut config = config();
+        assert!(last(&conn).unwrap().is_none());
+
+        // Unchanged configurations are not recorded.
+        let before = snapshot_of(&config);
+        assert!(!insert(&conn, &user, "/settings range", &before, &before).unwrap());
+        assert!(last(&conn).unwrap().is_none());
+
+        config.settings.ranks.ranks = vec![6];
+        let after = snapshot_of(&config);
+        assert!(insert(&conn, &user, "/settings range", &before, &after).unwrap());
+        let change = recent_in(&conn, 1).unwrap().remove(0);
+        assert_eq!(change.diff, "- settings.ranks.ranks[] = 5");
+
+        let (id, diff) = revert(&mut config, last(&conn).unwrap()).unwrap();
+        assert_eq!(id, change.id);
+        assert_eq!(diff, "+ settings.ranks.ranks[] = 5");
+        assert_eq!(config.settings.ranks.ranks, vec![5, 6]);
+        mark_undone(&conn, id).unwrap();
+        assert!(last(&conn).unwrap().is_none());
+    }
+
+    #[test]
+    fn roles_test() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let user = User::default();
+        let mut config = config();
+
+        let before = snapshot_of(&config);
+        config.roles.manager = Some(RoleId(3));
+        let after = snapshot_of(&config);
+        assert!(insert(&conn, &user, "/settings roles", &before, &after).unwrap());
+        let change = recent_in(&conn, 1).unwrap().remove(0);
+        assert_eq!(change.diff, "+ roles.manager = 3");
+
+        let (_, diff) = revert(&mut config, last(&conn).unwrap()).unwrap();
+        assert_eq!(diff, "- roles.manager = 3");
+        assert_eq!(config.roles.manager, None);
+    }
+
+    #[test]
+    fn undo_conflict_test() {
+        let conn = sqlite::open(":memory:").unwrap();
+        let user = User::default();
+        let mut config = config();
+
+        let before = snapshot_of(&config);
+        config.settings.ranks.ranks = vec![6];
+        let after = snapshot_of(&config);
+        insert(&conn, &user, "/settings range", &before, &after).unwrap();
+
+        // Moving bow from excluded to target only swaps two lines of the snapshot.
+        config.settings.excluded.weapon.clear();
+        config
+            .settings
+            .target
+            .weapon
+            .insert(crate::data::Weapon::Bow);
+        assert!(revert(&mut config, last(&conn).unwrap()).is_err());
+        assert_eq!(config.settings.ranks.ranks, vec![6]);
+    }
+}
@@ -0,0 +1,71 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use crate::{error::PermissionError, global::CONFIG, model::response::Invoker};
+use roulette_macros::bailout;
+use serenity::model::permissions::Permissions;
+use strum_macros::ToString;
+
+/// Authority levels, in ascending order.
+#[derive(Debug, Copy, Clone, Ord, PartialOrd, Eq, PartialEq, ToString)]
+pub(crate) enum Authority {
+    /// Anyone (read-only commands)
+    Everyone,
+    /// Members with the manager role
+    Manager,
+    /// Server administrators or members with the admin role
+    Admin,
+}
+
+impl Authority {
+    /// Returns the highest authority of `invoker`.
+    pub(crate) fn of(invoker: &Invoker) -> Authority {
+        let roles = &CONFIG.lock().unwrap().roles;
+        let has_role = |role: Option<_>| role.map_or(false, |role| invoker.roles.contains(&role));
+        if invoker.permissions.map_or(false, |permissions| {
+            permissions.contains(Permissions::ADMINISTRATOR)
+        }) || has_role(roles.admin)
+        {
+            Authority::Admin
+        } else if has_role(roles.manager) {
+            Authority::Manager
+        } else {
+            Authority::Everyone
+        }
+    }
+}
+
+/// Fails with [PermissionError::Denied] unless `invoker` has `required` authority.
+///
+/// Locks CONFIG, so callers must not hold CONN while calling it.
+/// Executors take the locks in the order CONFIG, CONN (e.g. `settings members`);
+/// one locking them the other way around must release CONN before locking CONFIG.
+pub(crate) fn require(invoker: &Invoker, required: Authority, command: &str) -> anyhow::Result<()> {
+    if Authority::of(invoker) < required {
+        bailout!(
+            "permission denied",
+            PermissionError::Denied {
+                user: invoker.user.name.clone(),
+                command: command.to_owned(),
+                required: required.to_string(),
+            }
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,232 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use crate::{
+    error::{CommandError, QueryError},
+    global::{CONN, QUESTS},
+    model::{
+        request::{Message, Request},
+        response::{Invoker, ProfileSubCommands, Response},
+        translate::TranslateTo,
+    },
+};
+use itertools::Itertools;
+use roulette_macros::bailout;
+use serenity::{builder::CreateEmbed, model::id::UserId, utils::Colour};
+use sqlite::Connection;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Index of ★7 quests unlocked by HR in [QUESTS]
+pub(crate) const HR_GATED_RANK: usize = 8;
+/// HR required for [HR_GATED_RANK]
+pub(crate) const HR_GATE: u32 = 8;
+
+/// Progress of a hunter.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Profile {
+    /// Hunter rank
+    pub hr: u32,
+    /// Highest unlocked star rank (★0..★7)
+    pub star: u32,
+}
+
+impl Profile {
+    /// Returns true if the hunter can join quests of `rank` (an index into [QUESTS]).
+    pub(crate) fn can_access(&self, rank: usize) -> bool {
+        if rank == HR_GATED_RANK {
+            self.star >= 7 && self.hr >= HR_GATE
+        } else {
+            rank <= self.star as usize
+        }
+    }
+
+    /// Ranks the hunter can join.
+    fn accessible_ranks(&self) -> Vec<usize> {
+        (0..QUESTS.len())
+            .filter(|rank| !QUESTS[*rank].is_empty() && self.can_access(*rank))
+            .collect()
+    }
+}
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error("PRAGMA table_info(hunters)")]
+    TableInfo,
+    #[error("ALTER TABLE hunters ADD COLUMN {column} INTEGER")]
+    AddColumn { column: &'static str },
+    #[error(
+        r#"
+        INSERT INTO hunters (id, name, hr, star) VALUES (?, ?, ?, ?)
+            ON CONFLICT (id)
+                DO UPDATE SET
+                    name = excluded.name,
+                    hr = excluded.hr,
+                    star = excluded.star,
+                    updated_at = datetime('now', 'localtime')
+    "#
+    )]
+    UpsetProfile,
+    #[error("SELECT id, hr, star FROM hunters WHERE hr IS NOT NULL AND id IN ({ids})")]
+    SelectProfiles { ids: String },
+}
+
+/// Adds the profile columns to the hunters table if they are missing.
+pub(crate) fn ensure_columns(conn: &Connection) -> anyhow::Result<()> {
+    let mut columns = vec![];
+    conn.iterate(format!("{}", Query::TableInfo), |pairs| {
+        columns.extend(
+            pairs
+                .iter()
+                .filter(|(key, _)| *key == "name")
+                .filter_map(|(_, value)| value.map(str::to_string)),
+        );
+        true
+    })
+    .map_err(|err| QueryError::FailedToAggregate {
+        raw: format!("{err}"),
+        query: format!("{}", Query::TableInfo),
+    })?;
+    for column in ["hr", "star"] {
+        if !columns.iter().any(|name| name == column) {
+            let query = Query::AddColumn { column };
+            conn.execute(format!("{query}"))
+                .map_err(|err| QueryError::FailedToStore {
+                    raw: format!("{err}"),
+                    query: format!("{query}"),
+                })?;
+        }
+    }
+    Ok(())
+}
+
+/// Returns profiles of `ids`. Hunters without a profile are absent from the result.
+pub(crate) fn profiles(
+    conn: &Connection,
+    ids: &[UserId],
+) -> anyhow::Result<HashMap<UserId, Profile>> {
+    ensure_columns(conn)?;
+    let mut profiles = HashMap::new();
+    if ids.is_empty() {
+        return Ok(profiles);
+    }
+    let query = Query::SelectProfiles {
+        ids: ids.iter().map(|id| id.0.to_string()).join(", "),
+    };
+    conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, Some(id)), (_, Some(hr)), (_, star)] = pairs {
+            if let (Ok(id), Ok(hr)) = (id.parse::<u64>(), hr.parse::<u32>()) {
+                let star = star.and_then(|star| star.parse().ok()).unwrap_or_default();
+                profiles.insert(UserId(id), Profile { hr, star });
+            }
+        }
+        true
+    })
+    .map_err(|err| QueryError::FailedToAggregate {
+        raw: format!("{err}"),
+        query: format!("{query}"),
+    })?;
+    Ok(profiles)
+}
+
+/// # profile command
+///
+/// ## sub-commands
+/// - set [hr] [rank]: stores the invoker's HR and highest unlocked star rank
+/// - show [user]: shows the profile of the user (or the invoker)
+///
+/// Quest generation only picks ranks every selected member can access.
+pub fn profile(items: &[Response], invoker: &Invoker) -> anyhow::Result<Request> {
+    match items.translate_to::<ProfileSubCommands>()? {
+        ProfileSubCommands::Set { hr, star } => set(invoker, hr, star),
+        ProfileSubCommands::Show(user) => show(user.map_or(invoker.user.id, |user| user.id)),
+    }
+}
+
+fn set(invoker: &Invoker, hr: i64, star: i64) -> anyhow::Result<Request> {
+    if !(1..=999).contains(&hr) {
+        bailout!(
+            "Invalid HR",
+            CommandError::InvalidArgument {
+                arg: format!("hr: {hr}"),
+            }
+        );
+    }
+    if !(0..=7).contains(&star) {
+        bailout!(
+            "Invalid rank",
+            CommandError::InvalidArgument {
+                arg: format!("rank: {star}"),
+            }
+        );
+    }
+    {
+        let conn = CONN.lock().unwrap();
+        ensure_columns(&conn)?;
+        (|| {
+            let mut statement = conn.prepare(format!("{}", Query::UpsetProfile))?;
+            statement.bind(1, invoker.user.id.0 as i64)?;
+            statement.bind(2, invoker.user.name.as_str())?;
+            statement.bind(3, hr)?;
+            statement.bind(4, star)?;
+            statement.next().map(|_| ())
+        })()
+        .map_err(|err| QueryError::FailedToStore {
+            raw: format!("{err}"),
+            query: format!("{}", Query::UpsetProfile),
+        })?;
+    }
+    Ok(show(invoker.user.id)?.ephemeral())
+}
+
+fn show(id: UserId) -> anyhow::Result<Request> {
+    let profile = profiles(&CONN.lock().unwrap(), &[id])?.remove(&id);
+    let mut embed = CreateEmbed::default();
+    embed.colour(Colour::MEIBE_PINK).title("profile");
+    match profile {
+        Some(profile) => {
+            embed
+                .description(format!("<@{}>", id.0))
+                .field("HR", profile.hr, true)
+                .field("unlocked", format!("★{}", profile.star), true)
+                .field(
+                    "accessible quest ranks",
+                    profile
+                        .accessible_ranks()
+                        .iter()
+                        .map(|rank| {
+                            if *rank == HR_GATED_RANK {
+                                "★7 (HR)".to_string()
+                            } else {
+                                format!("★{rank}")
+                            }
+                        })
+                        .join(", "),
+                    false,
+                );
+        }
+        None => {
+            embed.description(format!(
+                "<@{}> has no profile yet. Every quest rank is considered accessible.",
+                id.0
+            ));
+        }
+    }
+    Ok(Request::Message(Message::Embed(embed)))
+}
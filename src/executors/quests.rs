@@ -0,0 +1,176 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use itertools::Itertools;
+use serenity::{
+    builder::{CreateButton, CreateEmbed},
+    model::interactions::message_component::ButtonStyle,
+    utils::Colour,
+};
+
+use crate::{
+    data::QuestID,
+    error::CommandError,
+    global::{CONFIG, QUESTS},
+    model::{
+        request::{Buttons, Component, Message, Request, SelectMenuOption},
+        response::Response,
+        translate::TranslateTo,
+    },
+};
+use roulette_macros::bailout;
+
+/// # quests command
+///
+/// ## options
+/// - rank (optional): quest rank to show first
+pub fn quests(items: &[Response]) -> anyhow::Result<Request> {
+    match items {
+        [] => {
+            let (embed, components) = page(ranks().next().unwrap_or_default())?;
+            Ok(Request::Interactive {
+                message: Message::Embed(embed),
+                components,
+            })
+        }
+        [rank] => {
+            let rank = rank.translate_to::<i64>()?;
+            if !ranks().any(|r| r as i64 == rank) {
+                bailout!(
+                    "no quests",
+                    CommandError::InvalidArgument {
+                        arg: format!("rank: {rank}"),
+                    }
+                );
+            }
+            let (embed, components) = page(rank as usize)?;
+            Ok(Request::Interactive {
+                message: Message::Embed(embed),
+                components,
+            })
+        }
+        _ => Err(anyhow::anyhow!("invalid : {:?}", items)),
+    }
+}
+
+/// Ranks that have at least one quest in the catalog.
+fn ranks() -> impl Iterator<Item = usize> {
+    QUESTS
+        .iter()
+        .enumerate()
+        .filter(|(_, quests)| !quests.is_empty())
+        .map(|(rank, _)| rank)
+}
+
+/// Turns the page of the quest catalog to `rank`.
+pub fn quests_page(rank: usize) -> anyhow::Result<Request> {
+    let (embed, components) = page(rank)?;
+    Ok(Request::Update {
+        content: String::new(),
+        embeds: vec![embed],
+        components,
+    })
+}
+
+/// Renders quests of `rank` with previous/next buttons and a rank select menu.
+fn page(rank: usize) -> anyhow::Result<(CreateEmbed, Vec<Component>)> {
+    let quests = QUESTS
+        .get(rank)
+        .filter(|quests| !quests.is_empty())
+        .ok_or_else(|| CommandError::InvalidArgument {
+            arg: format!("rank: {rank}"),
+        })?;
+
+    let description = {
+        let settings = &CONFIG.lock().unwrap().settings;
+        quests
+            .iter()
+            .enumerate()
+            .map(|(number, quest)| {
+                let id = QuestID(rank as u32, number as u32);
+                let mark = if settings.target.quest.contains(&id) {
+                    "🎯"
+                } else if settings.excluded.quest.contains(&id) {
+                    "🚫"
+                } else {
+                    "▫️"
+                };
+                format!(
+                    "{mark} `{id}` **{title}**\n\u{3000}{monsters}",
+                    title = quest.title(),
+                    monsters = quest.objective(),
+                )
+            })
+            .join("\n")
+    };
+
+    let ranks = ranks().collect_vec();
+    let page = ranks.iter().position(|r| *r == rank).unwrap_or_default();
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::DARK_GREEN)
+        .title(format!("★{rank} quests"))
+        .description(description)
+        .footer(|footer| {
+            footer.text(format!(
+                "🎯 targeted / 🚫 excluded | page {}/{}",
+                page + 1,
+                ranks.len()
+            ))
+        });
+
+    let mut prev = CreateButton::default();
+    prev.style(ButtonStyle::Secondary)
+        .label("◀ previous")
+        .custom_id(format!(
+            "quests:prev:{}",
+            page.checked_sub(1).map_or(rank, |prev| ranks[prev])
+        ))
+        .disabled(page == 0);
+    let mut next = CreateButton::default();
+    next.style(ButtonStyle::Secondary)
+        .label("next ▶")
+        .custom_id(format!(
+            "quests:next:{}",
+            ranks.get(page + 1).copied().unwrap_or(rank)
+        ))
+        .disabled(page + 1 == ranks.len());
+
+    Ok((
+        embed,
+        vec![
+            Component::Buttons(Buttons::new(&[prev, next])),
+            Component::SelectMenu {
+                custom_id: "quests".to_string(),
+                min_value: 1,
+                max_value: 1,
+                options: ranks
+                    .iter()
+                    .map(|rank| SelectMenuOption {
+                        description: format!("{} quest(s)", QUESTS[*rank].len()),
+                        label: format!("★{rank}"),
+                        value: rank.to_string(),
+                    })
+                    .collect_vec(),
+                disabled: false,
+            },
+        ],
+    ))
+}
@@ -0,0 +1,281 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Server-wide leaderboards of `/statistics ranking`.
+
+use super::{
+    rolls,
+    statistics::{valid_period, valid_weapon, Period},
+    time_zone::{local_datetime, time_zone, today, Zone},
+    utility::hunter_names,
+};
+use crate::{
+    error::{CommandError, QueryError},
+    global::CONN,
+    model::{
+        request::{Buttons, Component, Message, Request},
+        response::{Invoker, RankingBy},
+    },
+};
+use chrono::NaiveDate;
+use indoc::indoc;
+use itertools::Itertools;
+use roulette_macros::bailout;
+use serenity::{
+    builder::{CreateButton, CreateEmbed},
+    model::{id::UserId, interactions::message_component::ButtonStyle},
+    utils::Colour,
+};
+
+/// Hunters per page
+const PAGE_SIZE: usize = 10;
+
+/// Ranking to show, encoded into `custom_id`s of the page buttons.
+#[derive(Debug)]
+struct Ranking {
+    by: RankingBy,
+    weapon: Option<String>,
+    period: Period,
+}
+
+impl Ranking {
+    /// Encodes the ranking as `<by>,<weapon>,<since>,<until>` (empty if absent).
+    fn encode(&self) -> String {
+        let date = |date: Option<NaiveDate>| date.map(|date| date.to_string()).unwrap_or_default();
+        format!(
+            "{by},{weapon},{since},{until}",
+            by = self.by.as_ref(),
+            weapon = self.weapon.as_deref().unwrap_or_default(),
+            since = date(self.period.0),
+            until = date(self.period.1),
+        )
+    }
+
+    fn decode(state: &str) -> anyhow::Result<Ranking> {
+        let invalid = || {
+            anyhow::Error::from(CommandError::InvalidArgument {
+                arg: format!("ranking: {state}"),
+            })
+        };
+        let date = |date: &str| {
+            (!date.is_empty())
+                .then(|| date.parse::<NaiveDate>())
+                .transpose()
+                .map_err(|_| invalid())
+        };
+        match state.split(',').collect_vec()[..] {
+            [by, weapon, since, until] => Ok(Ranking {
+                by: by.parse().map_err(|_| invalid())?,
+                weapon: (!weapon.is_empty()).then(|| weapon.to_string()),
+                period: (date(since)?, date(until)?),
+            }),
+            _ => Err(invalid()),
+        }
+    }
+
+    fn title(&self) -> String {
+        let by = match self.by {
+            RankingBy::Rolls => "rolls".to_string(),
+            RankingBy::Weapon => {
+                format!("rolls with {}", self.weapon.as_deref().unwrap_or_default())
+            }
+            RankingBy::Clears => "clears".to_string(),
+            RankingBy::Variety => "weapon variety".to_string(),
+        };
+        match self.period {
+            (None, None) => format!("ranking by {by}"),
+            (since, until) => format!(
+                "ranking by {by} ({} ~ {})",
+                since.map(|date| date.to_string()).unwrap_or_default(),
+                until.map(|date| date.to_string()).unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// Query of hunter ids and their scores, best first.
+    fn query(&self, tz: Zone) -> String {
+        let mut conditions = vec!["1".to_string()];
+        if let Some(weapon) = &self.weapon {
+            conditions.push(format!("logs.weapon = '{weapon}'"));
+        }
+        if let Some(since) = self.period.0 {
+            conditions.push(format!(
+                "date({}) >= '{since}'",
+                local_datetime("logs.generated_at", tz)
+            ));
+        }
+        if let Some(until) = self.period.1 {
+            conditions.push(format!(
+                "date({}) <= '{until}'",
+                local_datetime("logs.generated_at", tz)
+            ));
+        }
+        let (score, join) = match self.by {
+            RankingBy::Rolls | RankingBy::Weapon => ("COUNT(*)", ""),
+            RankingBy::Clears => (
+                "COUNT(*)",
+                "JOIN rolls ON rolls.id = logs.roll_id AND rolls.cleared = 1",
+            ),
+            RankingBy::Variety => ("COUNT(DISTINCT logs.weapon)", ""),
+        };
+        format!(
+            indoc! {r#"
+                SELECT logs.id AS id, {score} AS score
+                FROM logs {join}
+                WHERE {conditions}
+                GROUP BY logs.id
+                ORDER BY score DESC, logs.id
+            "#},
+            score = score,
+            join = join,
+            conditions = conditions.join(" AND "),
+        )
+    }
+}
+
+/// Ranks all hunters in the database.
+///
+/// Scores are counted from the logs, and periods follow the time zone of the guild.
+pub(crate) fn ranking(
+    by: RankingBy,
+    weapon: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
+    let tz = time_zone(invoker.guild_id);
+    let weapon = match (by, weapon) {
+        (RankingBy::Weapon, Some(weapon)) => match &valid_weapon(&weapon)?[..] {
+            [weapon] => Some(weapon.clone()),
+            _ => bailout!(
+                "Multiple weapons",
+                CommandError::InvalidArgument {
+                    arg: format!("weapon: {weapon} (choose one weapon key)"),
+                }
+            ),
+        },
+        (RankingBy::Weapon, None) => bailout!(
+            "No weapon",
+            CommandError::InvalidArgument {
+                arg: "weapon is required to rank by weapon".to_string(),
+            }
+        ),
+        // Only the weapon ranking is narrowed down to a weapon.
+        (_, _) => None,
+    };
+    let ranking = Ranking {
+        by,
+        weapon,
+        period: valid_period(since, until, today(tz))?,
+    };
+    let (embed, components) = page(&ranking, 0, tz)?;
+    Ok(Request::Interactive {
+        message: Message::Embed(embed),
+        components,
+    })
+}
+
+/// Turns the page of the ranking encoded in `state`.
+pub fn ranking_page(page_number: usize, state: &str, invoker: &Invoker) -> anyhow::Result<Request> {
+    let ranking = Ranking::decode(state)?;
+    let (embed, components) = page(&ranking, page_number, time_zone(invoker.guild_id))?;
+    Ok(Request::Update {
+        content: String::new(),
+        embeds: vec![embed],
+        components,
+    })
+}
+
+/// Renders a page of the ranking with previous/next buttons.
+fn page(
+    ranking: &Ranking,
+    page_number: usize,
+    tz: Zone,
+) -> anyhow::Result<(CreateEmbed, Vec<Component>)> {
+    let query = ranking.query(tz);
+    let (scores, names) = {
+        let conn = CONN.lock().unwrap();
+        rolls::ensure_tables(&conn)?;
+        let mut scores: Vec<(UserId, i64)> = vec![];
+        if let Err(err) = conn.iterate(&query, |pairs| {
+            if let [(_, Some(id)), (_, Some(score))] = pairs {
+                if let (Ok(id), Ok(score)) = (id.parse::<u64>(), score.parse::<i64>()) {
+                    scores.push((UserId(id), score));
+                }
+            }
+            true
+        }) {
+            bailout!(
+                "query error",
+                QueryError::FailedToAggregate {
+                    raw: format!("{err}"),
+                    query
+                }
+            );
+        }
+        let ids = scores.iter().map(|(id, _)| *id).collect_vec();
+        let names = hunter_names(&conn, &ids)?;
+        (scores, names)
+    };
+
+    let pages = (scores.len() + PAGE_SIZE - 1) / PAGE_SIZE;
+    let page_number = page_number.min(pages.saturating_sub(1));
+    let lines = scores
+        .iter()
+        .enumerate()
+        .skip(page_number * PAGE_SIZE)
+        .take(PAGE_SIZE)
+        .map(|(index, (id, score))| {
+            // Tied hunters share the place of the first of them.
+            let place = scores
+                .iter()
+                .position(|(_, first)| first == score)
+                .unwrap_or(index)
+                + 1;
+            format!(
+                "**{place}.** {name} — {score}",
+                name = names.get(id).cloned().unwrap_or_else(|| id.to_string()),
+            )
+        })
+        .join("\n");
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title(ranking.title())
+        .description(if lines.is_empty() {
+            "No roll has been recorded.".to_string()
+        } else {
+            lines
+        })
+        .footer(|footer| footer.text(format!("page {}/{}", page_number + 1, pages.max(1))));
+
+    let state = ranking.encode();
+    let mut prev = CreateButton::default();
+    prev.style(ButtonStyle::Secondary)
+        .label("◀ previous")
+        .custom_id(format!("ranking:{}:{state}", page_number.saturating_sub(1)))
+        .disabled(page_number == 0);
+    let mut next = CreateButton::default();
+    next.style(ButtonStyle::Secondary)
+        .label("next ▶")
+        .custom_id(format!("ranking:{}:{state}", page_number + 1))
+        .disabled(page_number + 1 >= pages);
+
+    Ok((embed, vec![Component::Buttons(Buttons::new(&[prev, next]))]))
+}
@@ -0,0 +1,309 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Anti-repeat of `/generate`: recent weapons of each member and recent targets of the guild.
+//!
+//! When every candidate has been rolled recently, the constraint is relaxed by forgetting
+//! the oldest entries one by one until some candidate remains.
+//! Constraints are relaxed in this order (see [avoid_all]):
+//!
+//! 1. the quest within the chosen rank, or the monster
+//! 2. weapons of each member, after coverage priorities narrowed them down
+
+use super::{
+    rolls::{self, Target},
+    utility::failed_to_aggregate,
+};
+use crate::data::{Recency, Weapon};
+use serenity::model::id::UserId;
+use sqlite::Connection;
+use std::collections::HashMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error("SELECT weapon FROM logs WHERE id = {id} ORDER BY rowid DESC LIMIT {limit}")]
+    RecentWeapons { id: u64, limit: usize },
+    #[error(
+        r#"
+        SELECT quest, monster FROM rolls
+            WHERE id IN (SELECT roll_id FROM logs WHERE roll_id IS NOT NULL)
+            ORDER BY id DESC
+            LIMIT {limit}
+    "#
+    )]
+    RecentTargets { limit: usize },
+}
+
+/// Recent history read from `logs`, newest first.
+#[derive(Debug, Default)]
+pub(crate) struct Recent {
+    weapons: HashMap<UserId, Vec<Weapon>>,
+    targets: Vec<Target>,
+}
+
+impl Recent {
+    /// Recent weapons of `member`.
+    pub(crate) fn weapons_of(&self, member: &UserId) -> Vec<Vec<Weapon>> {
+        self.weapons
+            .get(member)
+            .map(|weapons| weapons.iter().map(|weapon| vec![*weapon]).collect())
+            .unwrap_or_default()
+    }
+
+    /// Recent targets, where a quest also counts as the monsters in its mandatory order.
+    fn targets(&self) -> Vec<Vec<Target>> {
+        self.targets
+            .iter()
+            .map(|target| {
+                let monsters = target.monsters().into_iter().map(Target::Monster);
+                match target {
+                    Target::Quest(_) => std::iter::once(*target).chain(monsters).collect(),
+                    Target::Monster(_) => monsters.collect(),
+                }
+            })
+            .collect()
+    }
+}
+
+/// Weapon candidates of each member
+pub(crate) type MemberWeapons = Vec<(UserId, Vec<Weapon>)>;
+
+/// Anti-repeat constraint relaxed by [avoid_all].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Relaxation {
+    /// Only the last `kept` of `window` targets have been avoided.
+    Targets { kept: usize, window: usize },
+    /// Only the last `kept` of `window` weapons of `member` have been avoided.
+    Weapons {
+        member: UserId,
+        kept: usize,
+        window: usize,
+    },
+}
+
+/// Reads the last weapons of `members` and the last targets within `recency`.
+pub(crate) fn recent(
+    conn: &Connection,
+    members: &[UserId],
+    recency: Recency,
+) -> anyhow::Result<Recent> {
+    let mut recent = Recent::default();
+    if recency.weapons > 0 {
+        for member in members {
+            let query = Query::RecentWeapons {
+                id: member.0,
+                limit: recency.weapons,
+            };
+            let weapons = recent.weapons.entry(*member).or_default();
+            conn.iterate(format!("{query}"), |pairs| {
+                weapons.extend(
+                    pairs.first().and_then(|(_, value)| {
+                        value.and_then(|value| value.parse::<Weapon>().ok())
+                    }),
+                );
+                true
+            })
+            .map_err(|err| failed_to_aggregate(&query, err))?;
+        }
+    }
+    if recency.targets > 0 {
+        rolls::ensure_tables(conn)?;
+        let query = Query::RecentTargets {
+            limit: recency.targets,
+        };
+        conn.iterate(format!("{query}"), |pairs| {
+            if let [(_, quest), (_, monster)] = pairs {
+                recent
+                    .targets
+                    .extend(Target::from_columns(*quest, *monster));
+            }
+            true
+        })
+        .map_err(|err| failed_to_aggregate(&query, err))?;
+    }
+    Ok(recent)
+}
+
+/// Filters out recently rolled `targets` and `weapons` of each member.
+///
+/// Relaxes the targets first and then the weapons of each member.
+/// Returns the remaining candidates and the relaxed constraints in that order.
+pub(crate) fn avoid_all(
+    recent: &Recent,
+    targets: Vec<Target>,
+    weapons: MemberWeapons,
+) -> (Vec<Target>, MemberWeapons, Vec<Relaxation>) {
+    let mut relaxations = vec![];
+    let recent_targets = recent.targets();
+    let (targets, kept) = avoid(targets, &recent_targets);
+    if kept < recent_targets.len() {
+        relaxations.push(Relaxation::Targets {
+            kept,
+            window: recent_targets.len(),
+        });
+    }
+    let weapons = weapons
+        .into_iter()
+        .map(|(member, candidates)| {
+            let recent_weapons = recent.weapons_of(&member);
+            let (candidates, kept) = avoid(candidates, &recent_weapons);
+            if kept < recent_weapons.len() {
+                relaxations.push(Relaxation::Weapons {
+                    member,
+                    kept,
+                    window: recent_weapons.len(),
+                });
+            }
+            (member, candidates)
+        })
+        .collect();
+    (targets, weapons, relaxations)
+}
+
+/// Filters out `candidates` found in `recent` (newest first).
+///
+/// If no candidate remains, the oldest entries of `recent` are forgotten until some candidate
+/// remains. Returns the candidates and the number of the newest entries still avoided.
+fn avoid<T: PartialEq>(candidates: Vec<T>, recent: &[Vec<T>]) -> (Vec<T>, usize) {
+    if candidates.is_empty() {
+        return (candidates, recent.len());
+    }
+    for depth in (1..=recent.len()).rev() {
+        let is_recent = |candidate: &T| {
+            recent[..depth]
+                .iter()
+                .any(|entry| entry.contains(candidate))
+        };
+        if candidates.iter().any(|candidate| !is_recent(candidate)) {
+            return (
+                candidates
+                    .into_iter()
+                    .filter(|candidate| !is_recent(candidate))
+                    .collect(),
+                depth,
+            );
+        }
+    }
+    (candidates, 0)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        data::{Monster, QuestID},
+        global::QUESTS,
+    };
+    use strum::IntoEnumIterator;
+
+    fn recent(weapons: Vec<Weapon>, targets: Vec<Target>) -> Recent {
+        Recent {
+            weapons: std::iter::once((UserId(1), weapons)).collect(),
+            targets,
+        }
+    }
+
+    fn quests(numbers: &[u32]) -> Vec<Target> {
+        numbers
+            .iter()
+            .map(|number| Target::Quest(QuestID(5, *number)))
+            .collect()
+    }
+
+    #[test]
+    fn full_window_test() {
+        let recent = recent(vec![Weapon::Bow], quests(&[1, 2]));
+        let (targets, weapons, relaxations) = avoid_all(
+            &recent,
+            quests(&[1, 2, 3]),
+            vec![(UserId(1), vec![Weapon::Bow, Weapon::Lance])],
+        );
+        assert_eq!(targets, quests(&[3]));
+        assert_eq!(weapons, vec![(UserId(1), vec![Weapon::Lance])]);
+        assert!(relaxations.is_empty());
+    }
+
+    #[test]
+    fn partial_window_test() {
+        // Newest first: quest 2 was rolled after quest 1.
+        let recent = recent(vec![Weapon::Bow, Weapon::Lance], quests(&[2, 1]));
+        let (targets, weapons, relaxations) = avoid_all(
+            &recent,
+            quests(&[1, 2]),
+            vec![(UserId(1), vec![Weapon::Bow, Weapon::Lance])],
+        );
+        assert_eq!(targets, quests(&[1]));
+        assert_eq!(weapons, vec![(UserId(1), vec![Weapon::Lance])]);
+        assert_eq!(
+            relaxations,
+            vec![
+                Relaxation::Targets { kept: 1, window: 2 },
+                Relaxation::Weapons {
+                    member: UserId(1),
+                    kept: 1,
+                    window: 2
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn everything_excluded_test() {
+        let recent = recent(vec![Weapon::Bow], quests(&[1]));
+        let (targets, weapons, relaxations) =
+            avoid_all(&recent, quests(&[1]), vec![(UserId(1), vec![Weapon::Bow])]);
+        assert_eq!(targets, quests(&[1]));
+        assert_eq!(weapons, vec![(UserId(1), vec![Weapon::Bow])]);
+        assert_eq!(
+            relaxations,
+            vec![
+                Relaxation::Targets { kept: 0, window: 1 },
+                Relaxation::Weapons {
+                    member: UserId(1),
+                    kept: 0,
+                    window: 1
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn monsters_of_quests_test() {
+        let (id, monster) = QUESTS
+            .iter()
+            .enumerate()
+            .flat_map(|(rank, quests)| {
+                quests
+                    .iter()
+                    .enumerate()
+                    .map(move |(number, quest)| (QuestID(rank as u32, number as u32), quest))
+            })
+            .find_map(|(id, quest)| quest.monsters().first().map(|monster| (id, *monster)))
+            .unwrap();
+        let other = Monster::iter().find(|other| *other != monster).unwrap();
+        let recent = recent(vec![], vec![Target::Quest(id)]);
+        let (targets, _, relaxations) = avoid_all(
+            &recent,
+            vec![Target::Monster(monster), Target::Monster(other)],
+            vec![],
+        );
+        assert_eq!(targets, vec![Target::Monster(other)]);
+        assert!(relaxations.is_empty());
+    }
+}
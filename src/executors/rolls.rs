@@ -0,0 +1,228 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Rolls of `/generate`: their targets and whether the party cleared them.
+
+use crate::{
+    data::{Monster, QuestID},
+    error::CommandError,
+    executors::{
+        permission::{require, Authority},
+        utility::{failed_to_aggregate, failed_to_store},
+    },
+    global::CONN,
+    model::{
+        request::{Buttons, Component, Message, Request},
+        response::Invoker,
+    },
+};
+use roulette_macros::bailout;
+use serenity::{builder::CreateButton, model::interactions::message_component::ButtonStyle};
+use sqlite::Connection;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error(
+        r#"
+        CREATE TABLE IF NOT EXISTS rolls (
+            id           INTEGER PRIMARY KEY AUTOINCREMENT,
+            cleared      INTEGER NOT NULL DEFAULT 0,
+            generated_at TEXT    NOT NULL DEFAULT (datetime('now', 'localtime')),
+            quest        TEXT,
+            monster      TEXT
+        )
+    "#
+    )]
+    CreateTable,
+    #[error("PRAGMA table_info({table})")]
+    TableInfo { table: &'static str },
+    #[error("ALTER TABLE {table} ADD COLUMN {column} {definition}")]
+    AddColumn {
+        table: &'static str,
+        column: &'static str,
+        definition: &'static str,
+    },
+    #[error("INSERT INTO rolls (quest, monster) VALUES ({quest}, {monster})")]
+    InsertRoll { quest: String, monster: String },
+    #[error("SELECT last_insert_rowid() AS id")]
+    LastInsertId,
+    #[error("SELECT cleared FROM rolls WHERE id = {id}")]
+    SelectCleared { id: i64 },
+    #[error("SELECT id FROM logs WHERE roll_id = {id}")]
+    SelectParticipants { id: i64 },
+    #[error("UPDATE rolls SET cleared = 1 WHERE id = {id} AND cleared = 0")]
+    MarkCleared { id: i64 },
+}
+
+/// Target of a roll.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Target {
+    Quest(QuestID),
+    Monster(Monster),
+}
+
+impl Target {
+    /// Reads the target from the `quest` and `monster` columns of a roll.
+    pub(crate) fn from_columns(quest: Option<&str>, monster: Option<&str>) -> Option<Target> {
+        if let Some(quest) = quest {
+            return quest.parse().ok().map(Target::Quest);
+        }
+        monster
+            .and_then(|monster| monster.parse().ok())
+            .map(Target::Monster)
+    }
+
+    /// Monsters to hunt, including those in the mandatory order of a quest.
+    pub(crate) fn monsters(&self) -> Vec<Monster> {
+        match self {
+            Target::Quest(id) => id.quest().map(|quest| quest.monsters()).unwrap_or_default(),
+            Target::Monster(monster) => vec![*monster],
+        }
+    }
+}
+
+/// Columns added after their tables were created: `(table, column, definition)`
+const ADDED_COLUMNS: [(&str, &str, &str); 3] = [
+    ("logs", "roll_id", "INTEGER"),
+    ("rolls", "quest", "TEXT"),
+    ("rolls", "monster", "TEXT"),
+];
+
+/// Creates the rolls table and links logs to it if they are missing.
+pub(crate) fn ensure_tables(conn: &Connection) -> anyhow::Result<()> {
+    conn.execute(format!("{}", Query::CreateTable))
+        .map_err(|err| failed_to_store(Query::CreateTable, err))?;
+    for (table, column, definition) in ADDED_COLUMNS {
+        let mut exists = false;
+        conn.iterate(format!("{}", Query::TableInfo { table }), |pairs| {
+            exists |= pairs
+                .iter()
+                .any(|(key, value)| *key == "name" && *value == Some(column));
+            true
+        })
+        .map_err(|err| failed_to_aggregate(Query::TableInfo { table }, err))?;
+        if !exists {
+            let query = Query::AddColumn {
+                table,
+                column,
+                definition,
+            };
+            conn.execute(format!("{query}"))
+                .map_err(|err| failed_to_store(query, err))?;
+        }
+    }
+    Ok(())
+}
+
+/// Starts a new roll of `target` and returns its id.
+pub(crate) fn start(conn: &Connection, target: Target) -> anyhow::Result<i64> {
+    ensure_tables(conn)?;
+    let text =
+        |value: Option<String>| value.map_or_else(|| "NULL".to_string(), |v| format!("'{v}'"));
+    let query = match target {
+        Target::Quest(id) => Query::InsertRoll {
+            quest: text(Some(id.to_string())),
+            monster: text(None),
+        },
+        Target::Monster(monster) => Query::InsertRoll {
+            quest: text(None),
+            monster: text(Some(<&str>::from(monster).to_string())),
+        },
+    };
+    conn.execute(format!("{query}"))
+        .map_err(|err| failed_to_store(query, err))?;
+    let mut id = None;
+    conn.iterate(format!("{}", Query::LastInsertId), |pairs| {
+        id = pairs
+            .first()
+            .and_then(|(_, value)| value.and_then(|value| value.parse::<i64>().ok()));
+        true
+    })
+    .map_err(|err| failed_to_aggregate(Query::LastInsertId, err))?;
+    id.ok_or_else(|| anyhow::anyhow!("no roll has been inserted"))
+}
+
+/// Button to report that the party cleared the roll, disabled once `cleared_by` someone.
+pub(crate) fn clear_button(id: i64, cleared_by: Option<&str>) -> CreateButton {
+    let mut button = CreateButton::default();
+    button
+        .style(ButtonStyle::Success)
+        .label(cleared_by.map_or_else(
+            || "cleared".to_string(),
+            |name| format!("cleared by {name}"),
+        ))
+        .custom_id(format!("cleared:{id}"))
+        .disabled(cleared_by.is_some());
+    button
+}
+
+/// Marks the roll as cleared and disables the button.
+///
+/// Hunters of the roll can mark it, and the others require the manager authority.
+pub fn cleared(id: i64, invoker: &Invoker) -> anyhow::Result<Request> {
+    let (cleared, participants) = {
+        let conn = CONN.lock().unwrap();
+        ensure_tables(&conn)?;
+        let mut cleared = None;
+        conn.iterate(format!("{}", Query::SelectCleared { id }), |pairs| {
+            cleared = pairs.first().map(|(_, value)| *value != Some("0"));
+            true
+        })
+        .map_err(|err| failed_to_aggregate(Query::SelectCleared { id }, err))?;
+        let mut participants = vec![];
+        conn.iterate(format!("{}", Query::SelectParticipants { id }), |pairs| {
+            participants.extend(
+                pairs
+                    .first()
+                    .and_then(|(_, value)| value.and_then(|value| value.parse::<u64>().ok())),
+            );
+            true
+        })
+        .map_err(|err| failed_to_aggregate(Query::SelectParticipants { id }, err))?;
+        (cleared, participants)
+    };
+    let cleared = match cleared {
+        Some(cleared) => cleared,
+        None => bailout!(
+            "Unknown roll",
+            CommandError::InvalidArgument {
+                arg: format!("roll #{id}"),
+            }
+        ),
+    };
+    if !participants.contains(&invoker.user.id.0) {
+        require(invoker, Authority::Manager, "cleared")?;
+    }
+    // Another hunter may mark it in the meantime, so only the first update counts.
+    let marked = !cleared && {
+        let conn = CONN.lock().unwrap();
+        conn.execute(format!("{}", Query::MarkCleared { id }))
+            .map_err(|err| failed_to_store(Query::MarkCleared { id }, err))?;
+        conn.change_count() == 1
+    };
+    if !marked {
+        return Ok(Request::Message(Message::String(format!(
+            "roll #{id} has already been cleared"
+        )))
+        .ephemeral());
+    }
+    Ok(Request::UpdateComponents(vec![Component::Buttons(
+        Buttons::new(&[clear_button(id, Some(&invoker.user.name))]),
+    )]))
+}
@@ -0,0 +1,43 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use super::{history, settings::members};
+use crate::model::{
+    request::Request,
+    response::{Invoker, Options, Response, RouletteSubCommands},
+    translate::TranslateTo,
+};
+
+/// # roulette command
+///
+/// ## sub-commands
+/// - join: adds the invoker to the members
+/// - leave: removes the invoker from the members
+///
+/// Open to everyone, since hunters only change their own entry.
+pub fn roulette(items: &[Response], invoker: &Invoker) -> anyhow::Result<Request> {
+    let (option, command) = match items.translate_to::<RouletteSubCommands>()? {
+        RouletteSubCommands::Join => (Options::Add, "/roulette join"),
+        RouletteSubCommands::Leave => (Options::Remove, "/roulette leave"),
+    };
+    let before = history::snapshot();
+    let request = members(option, vec![invoker.user.clone()])?;
+    history::record(invoker, command, &before)?;
+    Ok(request)
+}
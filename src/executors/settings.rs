@@ -19,29 +19,54 @@
 
 #![allow(clippy::nonstandard_macro_braces)]
 use anyhow::Context;
+use chrono::Utc;
+use chrono_tz::Tz;
 use itertools::Itertools;
-use serenity::model::user::User;
+use serenity::{
+    model::{
+        guild::Role,
+        id::{RoleId, UserId},
+        user::User,
+    },
+    prelude::Mentionable,
+};
 use std::{
     collections::HashSet,
+    hash::Hash,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::Duration,
 };
 use thiserror::Error;
 
-use super::utility::JobStatus;
+use super::{
+    confirmation::{self, Operation},
+    guest::{guest, is_guest},
+    history,
+    permission::{require, Authority},
+    time_zone::{format_timestamp, time_zone, Zone},
+    utility::{fetch_users, field_value, hunter_names, role_members, JobStatus, TIME_LIMIT},
+    weights::probabilities,
+};
 use crate::{
-    data::{Monster, QuestID, TargetRank, Weapon},
+    data::{
+        is_valid_weight, Monster, QuestID, Recency, TargetRank, Weapon, MAX_RECENCY, MAX_WEIGHT,
+    },
     error::{CommandError, QueryError},
-    global::{sync_all, CONFIG, CONN, QUESTS},
+    global::{sync_all, CONFIG, CONN},
     model::{
         request::{Message, Request},
-        response::{About, Choices, Options, Response, SettingsSubCommands},
+        response::{
+            About, Choices, Invoker, Options, RecencyScope, Response, RoleKind, SettingsSubCommands,
+        },
         translate::TranslateTo,
     },
     parser::ValidateFor,
 };
+use indoc::indoc;
+use lazy_regex::regex_captures;
 use roulette_macros::bailout;
+use serenity::{builder::CreateEmbed, utils::Colour};
+use strum::IntoEnumIterator;
 
 use crate::{
     concepts::SameAs,
@@ -51,15 +76,16 @@ use crate::{
 /// # settings command
 ///
 /// ## sub-commands
+/// - help
 /// - info
 ///     - quest
 ///     - monster
 ///     - weapon
 ///     - members
 /// - members
-///     - set [user(s)]
-///     - add [user]
-///     - remove [user]
+///     - set [user(s)] [mentions] [role]
+///     - add [user(s)] [mentions] [role]
+///     - remove [user(s)] [mentions] [role]
 /// - range [lower] [upper]
 /// - exclude
 ///     - set [choice] [item(s)]
@@ -73,15 +99,370 @@ use crate::{
 ///     - quest
 ///     - monster
 ///     - weapon
-pub fn settings(items: &[Response]) -> anyhow::Result<Request> {
-    match items.translate_to::<SettingsSubCommands>()? {
+/// - history
+/// - undo
+/// - guest
+///     - add [name]
+///     - remove [name]
+///     - merge [name] [user]
+/// - timezone [zone]
+/// - recency [weapons/targets] [count]
+/// - weight [choice] [item(s)] [weight]
+/// - roles [admin/manager] [role]
+///
+/// Responses of settings command are visible only to the invoker.
+///
+/// `help`, `info`, `history`, and `timezone`, `recency` and `roles` without arguments are open
+/// to everyone. `obliterate` and `roles` with arguments require the admin authority,
+/// and the others require the manager authority.
+/// Every change is recorded to the audit log.
+/// `members set` and `obliterate` apply only after the invoker confirms the preview.
+pub fn settings(items: &[Response], invoker: &Invoker) -> anyhow::Result<Request> {
+    let sub_command = items.translate_to::<SettingsSubCommands>()?;
+    let (required, name) = match sub_command {
+        SettingsSubCommands::Help => (Authority::Everyone, "help"),
+        SettingsSubCommands::Info(_) => (Authority::Everyone, "info"),
+        SettingsSubCommands::Members { .. } => (Authority::Manager, "members"),
+        SettingsSubCommands::Range => (Authority::Manager, "range"),
+        SettingsSubCommands::Exclude(..) => (Authority::Manager, "exclude"),
+        SettingsSubCommands::Target(..) => (Authority::Manager, "target"),
+        SettingsSubCommands::Obliterate(_) => (Authority::Admin, "obliterate"),
+        SettingsSubCommands::History => (Authority::Everyone, "history"),
+        SettingsSubCommands::Undo => (Authority::Manager, "undo"),
+        SettingsSubCommands::Guest { .. } => (Authority::Manager, "guest"),
+        SettingsSubCommands::TimeZone(None) => (Authority::Everyone, "timezone"),
+        SettingsSubCommands::TimeZone(Some(_)) => (Authority::Manager, "timezone"),
+        SettingsSubCommands::Recency(None) => (Authority::Everyone, "recency"),
+        SettingsSubCommands::Recency(Some(_)) => (Authority::Manager, "recency"),
+        SettingsSubCommands::Weight(..) => (Authority::Manager, "weight"),
+        SettingsSubCommands::Roles(None) => (Authority::Everyone, "roles"),
+        SettingsSubCommands::Roles(Some(_)) => (Authority::Admin, "roles"),
+    };
+    let command = format!("/settings {name}");
+    require(invoker, required, &command)?;
+    let before = history::snapshot();
+    let request = match sub_command {
+        SettingsSubCommands::Help => Ok(help()?),
         SettingsSubCommands::Info(choice) => Ok(info(choice).unwrap()),
-        SettingsSubCommands::Members(opt, ref users) => members(opt, users.to_vec()),
+        SettingsSubCommands::Members {
+            option,
+            users,
+            mentions,
+            roles,
+        } => {
+            let users = collect_users(users, &mentions, &roles, invoker)?;
+            if let Options::Set = option {
+                let preview = members_preview(&users);
+                Ok(confirmation::ask(
+                    invoker,
+                    Operation::SetMembers(users),
+                    preview,
+                ))
+            } else {
+                members(option, users)
+            }
+        }
         SettingsSubCommands::Range => range(),
         SettingsSubCommands::Exclude(opt, choice, arg) => exclude(opt, choice, arg),
         SettingsSubCommands::Target(opt, choice, arg) => target(opt, choice, arg),
-        SettingsSubCommands::Obliterate(choice) => obliterate(choice),
+        SettingsSubCommands::Obliterate(choice) => Ok(confirmation::ask(
+            invoker,
+            Operation::Obliterate(choice),
+            obliterate_preview(choice),
+        )),
+        SettingsSubCommands::History => history(invoker),
+        SettingsSubCommands::Undo => undo(),
+        SettingsSubCommands::Guest { option, name, user } => guest(option, name, user),
+        SettingsSubCommands::TimeZone(zone) => time_zone_setting(zone, invoker),
+        SettingsSubCommands::Recency(window) => recency(window),
+        SettingsSubCommands::Weight(choice, arg, weight) => weight_setting(choice, arg, weight),
+        SettingsSubCommands::Roles(role) => roles(role),
+    }?;
+    // `undo` marks the change as undone instead of recording a new one.
+    if name != "undo" {
+        history::record(invoker, &command, &before)?;
     }
+    Ok(request.ephemeral())
+}
+
+/// Applies the operation waiting for confirmation of `token`.
+pub fn confirm(token: u64, invoker: &Invoker) -> anyhow::Result<Request> {
+    let operation = match confirmation::take(token, invoker)? {
+        Some(operation) => operation,
+        None => return Ok(expired()),
+    };
+    let (required, command) = match operation {
+        Operation::Obliterate(_) => (Authority::Admin, "/settings obliterate"),
+        Operation::SetMembers(_) => (Authority::Manager, "/settings members"),
+    };
+    require(invoker, required, command)?;
+    let before = history::snapshot();
+    let request = match operation {
+        Operation::Obliterate(choice) => obliterate(choice),
+        Operation::SetMembers(users) => members(Options::Set, users),
+    }?;
+    history::record(invoker, command, &before)?;
+    // Replace the preview with the result, removing the buttons.
+    Ok(match request {
+        Request::Message(Message::String(content)) => Request::Update {
+            content,
+            embeds: vec![],
+            components: vec![],
+        },
+        other => other,
+    })
+}
+
+/// Discards the operation waiting for confirmation of `token`.
+pub fn cancel(token: u64, invoker: &Invoker) -> anyhow::Result<Request> {
+    Ok(match confirmation::take(token, invoker)? {
+        Some(_) => Request::Update {
+            content: "Cancelled.".to_string(),
+            embeds: vec![],
+            components: vec![],
+        },
+        None => expired(),
+    })
+}
+
+fn expired() -> Request {
+    Request::Update {
+        content: "This confirmation has expired. Please run the command again.".to_string(),
+        embeds: vec![],
+        components: vec![],
+    }
+}
+
+/// Collects users given as user options, mentions (or IDs), and members of roles.
+fn collect_users(
+    mut users: Vec<User>,
+    mentions: &str,
+    roles: &[Role],
+    invoker: &Invoker,
+) -> anyhow::Result<Vec<User>> {
+    let ids = mentions
+        .split_whitespace()
+        .map(|mention| {
+            regex_captures!(r"^(?:<@!?([0-9]+)>|([0-9]+))$", mention)
+                .and_then(|(_, tagged, raw)| {
+                    (if tagged.is_empty() { raw } else { tagged })
+                        .parse::<u64>()
+                        .ok()
+                })
+                .map(UserId)
+                .ok_or_else(|| {
+                    anyhow::Error::from(CommandError::InvalidArgument {
+                        arg: mention.to_string(),
+                    })
+                })
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    users.extend(fetch_users(&ids)?);
+    for role in roles {
+        let guild_id = invoker
+            .guild_id
+            .with_context(|| anyhow::anyhow!("roles are available only in guilds"))?;
+        users.extend(role_members(guild_id, role.id)?);
+    }
+    if users.is_empty() {
+        bailout!(
+            "No users",
+            CommandError::InvalidArgument {
+                arg: "specify users, mentions or a role".to_string(),
+            }
+        );
+    }
+    Ok(users.into_iter().unique_by(|user| user.id).collect())
+}
+
+/// Mentions of `ids`, which Discord renders as the current display names.
+fn mentions<'a>(ids: impl Iterator<Item = &'a UserId>) -> String {
+    member_labels(ids).join(", ")
+}
+
+/// Mentions of Discord users, and names of guests who cannot be mentioned.
+fn member_labels<'a>(ids: impl Iterator<Item = &'a UserId>) -> Vec<String> {
+    let ids = ids.copied().sorted().collect_vec();
+    let guests = ids.iter().copied().filter(is_guest).collect_vec();
+    let names = hunter_names(&CONN.lock().unwrap(), &guests).unwrap_or_default();
+    ids.iter()
+        .map(|id| {
+            if is_guest(id) {
+                format!(
+                    "{} (guest)",
+                    names.get(id).cloned().unwrap_or_else(|| id.to_string())
+                )
+            } else {
+                id.mention().to_string()
+            }
+        })
+        .collect()
+}
+
+fn members_preview(users: &[User]) -> CreateEmbed {
+    let current = member_labels(CONFIG.lock().unwrap().members.iter());
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::ORANGE)
+        .title("settings members set")
+        .description("Members will be replaced as follows.")
+        .field("current", field_value(current, "\n"), true)
+        .field(
+            "new",
+            field_value(
+                users
+                    .iter()
+                    .map(|user| user.id)
+                    .sorted()
+                    .map(|id| id.mention().to_string()),
+                "\n",
+            ),
+            true,
+        );
+    embed
+}
+
+fn obliterate_preview(choice: Choices) -> CreateEmbed {
+    let (target, excluded): (Vec<String>, Vec<String>) = {
+        let settings = &CONFIG.lock().unwrap().settings;
+        match choice {
+            Choices::Quest => (
+                settings
+                    .target
+                    .quest
+                    .iter()
+                    .sorted()
+                    .map(ToString::to_string)
+                    .collect(),
+                settings
+                    .excluded
+                    .quest
+                    .iter()
+                    .sorted()
+                    .map(ToString::to_string)
+                    .collect(),
+            ),
+            Choices::Monster => (
+                settings
+                    .target
+                    .monster
+                    .iter()
+                    .map(|monster| monster.ja().to_string())
+                    .sorted()
+                    .collect(),
+                settings
+                    .excluded
+                    .monster
+                    .iter()
+                    .map(|monster| monster.ja().to_string())
+                    .sorted()
+                    .collect(),
+            ),
+            Choices::Weapon => (
+                settings
+                    .target
+                    .weapon
+                    .iter()
+                    .map(|weapon| weapon.ja().to_string())
+                    .sorted()
+                    .collect(),
+                settings
+                    .excluded
+                    .weapon
+                    .iter()
+                    .map(|weapon| weapon.ja().to_string())
+                    .sorted()
+                    .collect(),
+            ),
+        }
+    };
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::ORANGE)
+        .title(format!("settings obliterate {}", choice.as_ref()))
+        .description("The following candidates will be cleared.")
+        .field("target", field_value(target, "\n"), true)
+        .field("excluded", field_value(excluded, "\n"), true);
+    embed
+}
+
+fn help() -> anyhow::Result<Request, !> {
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title("settings")
+        .description(indoc! {"
+            You can configure members and candidates for the roulette.
+            `help` and `info` are open to everyone; the others require the manager or admin role.
+        "})
+        .field("info <about>", "Shows current configurations.", false)
+        .field(
+            "members <set/add/remove> [user(s)] [users] [role]",
+            "Changes members of the roulette (`set` asks for confirmation).",
+            false,
+        )
+        .field("range", "Selects target quest ranks.", false)
+        .field(
+            "exclude <set/add/remove> <quest/monster/weapon> <item(s)>",
+            "Changes excluded candidates.",
+            false,
+        )
+        .field(
+            "target <set/add/remove> <quest/monster/weapon> <item(s)>",
+            "Changes target candidates.",
+            false,
+        )
+        .field(
+            "obliterate <quest/monster/weapon>",
+            "Clears both target and excluded candidates (admin only, asks for confirmation).",
+            false,
+        )
+        .field(
+            "guest <add/remove/merge> <name> [user]",
+            "Adds or removes a guest member who is not a Discord user, \
+             or merges the guest's statistics into the user.",
+            false,
+        )
+        .field("history", "Shows recent configuration changes.", false)
+        .field("undo", "Reverts the last configuration change.", false)
+        .field(
+            "timezone [zone]",
+            "Shows or sets the time zone of this server (e.g. `Asia/Tokyo`), \
+             used for statistics periods and timestamps. \
+             `local` resets it to the local time of the host (default).",
+            false,
+        )
+        .field(
+            "recency [weapons/targets] [count]",
+            "Shows or sets how many of the last weapons of each member, \
+             and of the last quests or monsters of the server, `/generate` avoids (0 disables it).",
+            false,
+        )
+        .field(
+            "weight <quest/monster/weapon> <item(s)> <weight>",
+            "Changes how often `/generate` draws the items, e.g. `x0.2` or `x2` (1 resets it). \
+             `info` shows the resulting probabilities.",
+            false,
+        )
+        .field(
+            "roles [admin/manager] [role]",
+            "Shows or sets the roles allowed to change settings (admin only). \
+             The admin role has the authority of server administrators. \
+             Without a role, the given one is unset.",
+            false,
+        )
+        .field(
+            "quest selectors:",
+            indoc! {"
+                `5-12`: a single quest
+                `5-1..5-8`: quests between two IDs
+                `6-*`: all quests of a rank
+                `with:<monster>`: all quests with the monster
+                `<title>`: quests with the title
+            "},
+            false,
+        );
+    Ok(Request::Message(Message::Embed(embed)))
 }
 
 /// Returns information about `choice`.
@@ -93,12 +474,16 @@ fn info(about: About) -> anyhow::Result<Request, !> {
                 "Target quest(s): Random\n".to_string()
             } else {
                 format!(
-                    "Target quest(s):\n{}",
+                    "Target quest(s):\n{}\n",
                     settings
                         .target
                         .quest
                         .iter()
-                        .map(|id| { QUESTS[id.0 as usize][id.1 as usize].title() })
+                        .sorted()
+                        .map(|id| id.quest().map_or_else(
+                            || format!("{id}: (unknown)"),
+                            |quest| format!("{id}: {}", quest.title())
+                        ))
                         .join("\n")
                 )
             };
@@ -106,17 +491,51 @@ fn info(about: About) -> anyhow::Result<Request, !> {
                 "Excluded quest(s): No\n".to_string()
             } else {
                 format!(
-                    "Excluded quest(s):\n{}",
+                    "Excluded quest(s):\n{}\n",
                     settings
                         .excluded
                         .quest
                         .iter()
-                        .map(|id| format!("{}-{}", id.0, id.1))
+                        .sorted()
+                        .map(|id| id.quest().map_or_else(
+                            || format!("{id}: (unknown)"),
+                            |quest| format!("{id}: {}", quest.title())
+                        ))
                         .join("\n")
                 )
             };
+            // Ranks without quests are never drawn (see `/generate`).
+            let ranks = settings
+                .ranks
+                .ranks
+                .iter()
+                .map(|rank| {
+                    QuestID::all()
+                        .filter(|id| id.0 as usize == *rank)
+                        .collect_vec()
+                })
+                .filter(|candidates| !candidates.is_empty())
+                .collect_vec();
+            let probabilities = ranks
+                .iter()
+                .flat_map(|candidates| {
+                    let rank = candidates[0].0;
+                    probability_lines(
+                        candidates,
+                        |id| settings.weights.quest(id),
+                        |id| {
+                            id.quest().map_or_else(
+                                || format!("{id}: (unknown)"),
+                                |quest| format!("{id}: {}", quest.title()),
+                            )
+                        },
+                        1.0 / ranks.len() as f64,
+                        &format!("other quests of ★{rank}"),
+                    )
+                })
+                .join("\n");
             Message::String(format!(
-                "target quest rank: {ranks}\n{target}{excluded}",
+                "target quest rank: {ranks}\n{target}{excluded}{probabilities}",
                 ranks = settings
                     .ranks
                     .ranks
@@ -125,6 +544,7 @@ fn info(about: About) -> anyhow::Result<Request, !> {
                     .join(", "),
                 target = target_quests,
                 excluded = excluded_quests,
+                probabilities = format!("Probabilities per roll:\n{probabilities}"),
             ))
         }
         About::Monster => {
@@ -133,7 +553,7 @@ fn info(about: About) -> anyhow::Result<Request, !> {
                 "Target monster(s): Random\n".to_string()
             } else {
                 format!(
-                    "Target monster(s):\n{}",
+                    "Target monster(s):\n{}\n",
                     settings.target.monster.iter().map(Monster::ja).join("\n")
                 )
             };
@@ -141,30 +561,51 @@ fn info(about: About) -> anyhow::Result<Request, !> {
                 "Excluded monster(s): No\n".to_string()
             } else {
                 format!(
-                    "Excluded monster(s):\n{}",
+                    "Excluded monster(s):\n{}\n",
                     settings.excluded.monster.iter().map(Monster::ja).join("\n")
                 )
             };
+            let candidates = Monster::iter().collect_vec();
+            let probabilities = probability_lines(
+                &candidates,
+                |monster| settings.weights.monster(monster),
+                |monster| monster.ja().to_string(),
+                1.0,
+                "other monsters",
+            )
+            .join("\n");
             Message::String(format!(
-                "{target}{excluded}",
+                "{target}{excluded}Probabilities per roll:\n{probabilities}",
                 target = target_monsters,
                 excluded = excluded_monsters,
             ))
         }
         About::Weapon => {
             let settings = &CONFIG.lock().unwrap().settings;
-            if settings.excluded.weapon.is_empty() {
-                Message::String("Excluded weapon(s): No".to_string())
+            let excluded_weapons = if settings.excluded.weapon.is_empty() {
+                "Excluded weapon(s): No\n".to_string()
             } else {
-                Message::String(format!(
-                    "Excluded weapon(s):\n{}",
+                format!(
+                    "Excluded weapon(s):\n{}\n",
                     settings.excluded.weapon.iter().map(Weapon::ja).join("\n")
-                ))
-            }
+                )
+            };
+            let candidates = Weapon::iter().collect_vec();
+            let probabilities = probability_lines(
+                &candidates,
+                |weapon| settings.weights.weapon(weapon),
+                |weapon| weapon.ja().to_string(),
+                1.0,
+                "other weapons",
+            )
+            .join("\n");
+            Message::String(format!(
+                "{excluded_weapons}Probabilities per member:\n{probabilities}"
+            ))
         }
         About::Members => Message::String(format!(
             "Current members: {}",
-            CONFIG.lock().unwrap().members.iter().join(", ")
+            mentions(CONFIG.lock().unwrap().members.iter())
         )),
     }))
 }
@@ -184,7 +625,7 @@ enum Query {
 }
 
 /// Change current member as specified in `opt`.
-fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
+pub(crate) fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conf = Arc::clone(&*CONFIG);
@@ -192,25 +633,26 @@ fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut config) = conf.try_lock() {
-                let users: HashSet<_> = users.iter().cloned().collect();
+                let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    break Ok(());
+                }
+                let ids: HashSet<_> = users.iter().map(|user| user.id).collect();
                 match opt {
                     Options::Set => {
-                        config.members = users.clone();
+                        config.members = ids;
                     }
                     Options::Add => {
-                        for user in users.iter() {
-                            config.members.insert(user.clone());
-                        }
+                        config.members.extend(ids);
                     }
                     Options::Remove => {
-                        for user in users.iter() {
-                            config.members.remove(user);
+                        for id in ids.iter() {
+                            config.members.remove(id);
                         }
                     }
                 }
 
                 // We should Upset members name
-                let mut status = lock.lock().unwrap();
                 let conn = CONN.lock().unwrap();
 
                 for user in users.iter() {
@@ -237,8 +679,8 @@ fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(lock.lock().unwrap(), Duration::from_millis(100), |status| {
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
             *status == JobStatus::Pending
         })
         .unwrap();
@@ -247,11 +689,12 @@ fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
             handle.join().unwrap()?;
             break;
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "settings members".to_string(),
-                    wait_for: Duration::from_millis(100),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
@@ -264,18 +707,14 @@ fn members(opt: Options, users: Vec<User>) -> anyhow::Result<Request> {
         .context("sync_all failed.")
     })?;
     Ok(Request::Message(Message::String(format!(
-        "members = {:?}",
-        CONFIG
-            .lock()
-            .unwrap()
-            .members
-            .iter()
-            .map(|user| &user.name)
-            .collect::<Vec<_>>()
+        "members = {}",
+        mentions(CONFIG.lock().unwrap().members.iter())
     ))))
 }
 
-pub fn range_interaction(selected: Vec<usize>) -> anyhow::Result<Request> {
+pub fn range_interaction(selected: Vec<usize>, invoker: &Invoker) -> anyhow::Result<Request> {
+    require(invoker, Authority::Manager, "/settings range")?;
+    let before = history::snapshot();
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conf = Arc::clone(&*CONFIG);
@@ -283,8 +722,11 @@ pub fn range_interaction(selected: Vec<usize>) -> anyhow::Result<Request> {
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut config) = conf.try_lock() {
-                config.settings.ranks = TargetRank { ranks: selected };
                 let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    break;
+                }
+                config.settings.ranks = TargetRank { ranks: selected };
                 *status = JobStatus::ExitSuccess;
                 cvar.notify_one();
                 break;
@@ -293,8 +735,8 @@ pub fn range_interaction(selected: Vec<usize>) -> anyhow::Result<Request> {
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(lock.lock().unwrap(), Duration::from_millis(100), |status| {
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
             *status == JobStatus::Pending
         })
         .unwrap();
@@ -303,11 +745,12 @@ pub fn range_interaction(selected: Vec<usize>) -> anyhow::Result<Request> {
             handle.join().unwrap();
             break;
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "settings range".to_string(),
-                    wait_for: Duration::from_millis(100),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
@@ -319,22 +762,297 @@ pub fn range_interaction(selected: Vec<usize>) -> anyhow::Result<Request> {
         })
         .context("sync_all failed.")
     })?;
-    Ok(Request::Message(Message::String(
-        CONFIG
-            .lock()
-            .unwrap()
-            .settings
-            .ranks
-            .ranks
+    history::record(invoker, "/settings range", &before)?;
+    let ranks = CONFIG
+        .lock()
+        .unwrap()
+        .settings
+        .ranks
+        .ranks
+        .iter()
+        .map(|rank| format!("★{rank}"))
+        .join(", ");
+    // The select menu has been used, so disable it.
+    Ok(Request::Update {
+        content: format!("target quest rank: {ranks}"),
+        embeds: vec![],
+        components: vec![rank_select_menu(true)],
+    })
+}
+
+/// Shows recent configuration changes.
+fn history(invoker: &Invoker) -> anyhow::Result<Request> {
+    let tz = time_zone(invoker.guild_id);
+    let changes = history::recent(5)?;
+    let mut embed = CreateEmbed::default();
+    embed.colour(Colour::MEIBE_PINK).title("settings history");
+    if changes.is_empty() {
+        embed.description("No change has been recorded.");
+    }
+    for change in changes {
+        let title = format!(
+            "#{id} {command} by {user} ({created_at})",
+            id = change.id,
+            command = change.command,
+            user = change.user,
+            created_at = format_timestamp(&change.created_at, tz),
+        );
+        // Embed field values are limited to 1024 characters.
+        let diff = change.diff.chars().take(900).collect::<String>();
+        embed.field(
+            if change.undone {
+                format!("~~{title}~~ (undone)")
+            } else {
+                title
+            },
+            format!("```diff\n{diff}\n```"),
+            false,
+        );
+    }
+    Ok(Request::Message(Message::Embed(embed)))
+}
+
+/// Reverts the last configuration change.
+fn undo() -> anyhow::Result<Request> {
+    let (id, diff) = history::undo()?;
+    Ok(Request::Message(Message::String(format!(
+        "reverted change #{id}\n```diff\n{diff}\n```"
+    ))))
+}
+
+/// Shows the time zone of the guild, or sets it to `zone`, where `local` resets it to the host's.
+fn time_zone_setting(zone: Option<String>, invoker: &Invoker) -> anyhow::Result<Request> {
+    let guild_id = match invoker.guild_id {
+        Some(guild_id) => guild_id,
+        None => bailout!(
+            "Not in a guild",
+            CommandError::InvalidArgument {
+                arg: "time zones can be set only in servers".to_string(),
+            }
+        ),
+    };
+    let zone = match zone {
+        None => time_zone(Some(guild_id)),
+        Some(zone) => {
+            let zone = match zone.trim() {
+                "local" => Zone::Host,
+                name => match name.parse::<Tz>() {
+                    Ok(tz) => Zone::Named(tz),
+                    Err(_) => bailout!(
+                        "Unknown time zone",
+                        CommandError::InvalidArgument {
+                            arg: format!(
+                                "unknown time zone: {zone} (use a name like Asia/Tokyo, or local)"
+                            ),
+                        }
+                    ),
+                },
+            };
+            {
+                let time_zones = &mut CONFIG.lock().unwrap().time_zones;
+                match zone {
+                    Zone::Host => time_zones.remove(&guild_id.to_string()),
+                    Zone::Named(tz) => {
+                        time_zones.insert(guild_id.to_string(), tz.name().to_string())
+                    }
+                };
+            }
+            sync_all().map_err(|err| {
+                anyhow::Error::from(CommandError::FailedToSync {
+                    command: "settings timezone".to_string(),
+                    io_error: err,
+                })
+                .context("sync_all failed.")
+            })?;
+            zone
+        }
+    };
+    Ok(Request::Message(Message::String(format!(
+        "time zone: {name} (now {now})",
+        name = zone.name(),
+        now = zone.format(&Utc::now().naive_utc(), "%Y-%m-%d %H:%M %Z"),
+    ))))
+}
+
+/// Shows the admin and manager roles, or sets one of them to `role`, which unsets it if absent.
+fn roles(role: Option<(RoleKind, Option<Role>)>) -> anyhow::Result<Request> {
+    if let Some((kind, role)) = role {
+        {
+            let roles = &mut CONFIG.lock().unwrap().roles;
+            let id = role.map(|role| role.id);
+            match kind {
+                RoleKind::Admin => roles.admin = id,
+                RoleKind::Manager => roles.manager = id,
+            }
+        }
+        sync_all().map_err(|err| {
+            anyhow::Error::from(CommandError::FailedToSync {
+                command: "settings roles".to_string(),
+                io_error: err,
+            })
+            .context("sync_all failed.")
+        })?;
+    }
+    let roles = &CONFIG.lock().unwrap().roles;
+    let mention = |role: Option<RoleId>| {
+        role.map_or_else(|| "(none)".to_string(), |id| id.mention().to_string())
+    };
+    Ok(Request::Message(Message::String(format!(
+        "admin role: {admin}\nmanager role: {manager}",
+        admin = mention(roles.admin),
+        manager = mention(roles.manager),
+    ))))
+}
+
+/// Shows the anti-repeat windows, or sets one of them.
+fn recency(window: Option<(RecencyScope, i64)>) -> anyhow::Result<Request> {
+    let recency = match window {
+        None => CONFIG.lock().unwrap().settings.recency,
+        Some((scope, count)) => {
+            if !(0..=MAX_RECENCY as i64).contains(&count) {
+                bailout!(
+                    "Out of range",
+                    CommandError::InvalidArgument {
+                        arg: format!("recency must be between 0 and {MAX_RECENCY}: {count}"),
+                    }
+                );
+            }
+            let count = count as usize;
+            let recency = {
+                let mut config = CONFIG.lock().unwrap();
+                let recency = &mut config.settings.recency;
+                match scope {
+                    RecencyScope::Weapons => recency.weapons = count,
+                    RecencyScope::Targets => recency.targets = count,
+                }
+                *recency
+            };
+            sync_all().map_err(|err| {
+                anyhow::Error::from(CommandError::FailedToSync {
+                    command: "settings recency".to_string(),
+                    io_error: err,
+                })
+                .context("sync_all failed.")
+            })?;
+            recency
+        }
+    };
+    let Recency { weapons, targets } = recency;
+    Ok(Request::Message(Message::String(format!(
+        "members avoid their last {weapons} weapon(s), and the server avoids its last {targets} quest(s) or monster(s)"
+    ))))
+}
+
+/// Sets the weight of quest(s)/monster(s)/weapon(s) in `arg`, where `1` resets them.
+fn weight_setting(choice: Choices, arg: String, weight: String) -> anyhow::Result<Request> {
+    let value = match weight
+        .trim()
+        .trim_start_matches(&['x', '×'][..])
+        .parse::<f64>()
+    {
+        Ok(value) if is_valid_weight(value) => value,
+        _ => bailout!(
+            "Invalid weight",
+            CommandError::InvalidArgument {
+                arg: format!("weight must be greater than 0 and at most {MAX_WEIGHT}: {weight}"),
+            }
+        ),
+    };
+    let keys = match choice {
+        Choices::Quest => arg
+            .smart_cast::<QuestID>()?
             .iter()
-            .map(|rank| format!("★{rank}"))
-            .join(", "),
-    )))
+            .map(ToString::to_string)
+            .collect_vec(),
+        Choices::Monster => arg
+            .smart_cast::<Monster>()?
+            .into_iter()
+            .map(|monster| <&str>::from(monster).to_string())
+            .collect_vec(),
+        Choices::Weapon => arg
+            .smart_cast::<Weapon>()?
+            .into_iter()
+            .map(|weapon| <&str>::from(weapon).to_string())
+            .collect_vec(),
+    };
+    {
+        let mut config = CONFIG.lock().unwrap();
+        let weights = &mut config.settings.weights;
+        let section = match choice {
+            Choices::Quest => &mut weights.quest,
+            Choices::Monster => &mut weights.monster,
+            Choices::Weapon => &mut weights.weapon,
+        };
+        for key in keys.iter() {
+            if is_default_weight(value) {
+                section.remove(key);
+            } else {
+                section.insert(key.clone(), value);
+            }
+        }
+    }
+    sync_all().map_err(|err| {
+        anyhow::Error::from(CommandError::FailedToSync {
+            command: "settings weight".to_string(),
+            io_error: err,
+        })
+        .context("sync_all failed.")
+    })?;
+    Ok(Request::Message(Message::String(format!(
+        "set the weight of {count} {choice}(s) to ×{value}",
+        count = keys.len(),
+        choice = choice.as_ref(),
+    ))))
+}
+
+fn is_default_weight(weight: f64) -> bool {
+    (weight - 1.0).abs() < f64::EPSILON
+}
+
+/// Lines of the probabilities of weighted `candidates`, followed by that of the others.
+///
+/// `scale` is the probability that `candidates` are drawn from at all.
+fn probability_lines<T, F, N>(
+    candidates: &[T],
+    weight: F,
+    name: N,
+    scale: f64,
+    others: &str,
+) -> Vec<String>
+where
+    T: Copy,
+    F: Fn(T) -> f64,
+    N: Fn(T) -> String,
+{
+    let probabilities = probabilities(candidates, &weight);
+    let mut lines = probabilities
+        .iter()
+        .filter(|(candidate, _)| !is_default_weight(weight(*candidate)))
+        .map(|(candidate, p)| {
+            format!(
+                "{name}: {percent:.2}% (×{weight})",
+                name = name(*candidate),
+                percent = p * scale * 100.0,
+                weight = weight(*candidate),
+            )
+        })
+        .collect_vec();
+    if let Some((_, p)) = probabilities
+        .iter()
+        .find(|(candidate, _)| is_default_weight(weight(*candidate)))
+    {
+        lines.push(format!("{others}: {:.2}% each", p * scale * 100.0));
+    }
+    lines
 }
 
 /// Sets the range of target quest rank static_cast `[lower, upper]`.
 fn range() -> anyhow::Result<Request> {
-    Ok(Request::Components(Component::SelectMenu {
+    Ok(Request::Components(rank_select_menu(false)))
+}
+
+fn rank_select_menu(disabled: bool) -> Component {
+    Component::SelectMenu {
         custom_id: "range".to_string(),
         min_value: 1,
         max_value: 8,
@@ -345,7 +1063,8 @@ fn range() -> anyhow::Result<Request> {
                 value: rank.to_string(),
             })
             .collect_vec(),
-    }))
+        disabled,
+    }
 }
 
 trait SmartCast<T> {
@@ -396,10 +1115,38 @@ impl SmartCast<Weapon> for String {
     }
 }
 
+/// Quest(s)/monster(s)/weapon(s) given to `exclude` and `target`.
+enum Selection {
+    Quests(HashSet<QuestID>),
+    Monsters(HashSet<Monster>),
+    Weapons(HashSet<Weapon>),
+}
+
+impl Selection {
+    fn parse(choice: Choices, arg: String) -> anyhow::Result<Self> {
+        Ok(match choice {
+            Choices::Quest => Selection::Quests(arg.smart_cast::<QuestID>()?),
+            Choices::Monster => Selection::Monsters(arg.smart_cast::<Monster>()?),
+            Choices::Weapon => Selection::Weapons(arg.smart_cast::<Weapon>()?),
+        })
+    }
+}
+
+/// Sets, adds or removes `items` in `set` as specified in `opt`.
+fn update<T: Clone + Eq + Hash>(set: &mut HashSet<T>, items: &HashSet<T>, opt: Options) {
+    match opt {
+        Options::Set => *set = items.clone(),
+        Options::Add => set.extend(items.iter().cloned()),
+        Options::Remove => set.retain(|item| !items.contains(item)),
+    }
+}
+
 /// Configure excluded quest(s)/monster(s)/weapon(s).
 /// - set/add/remove: as specified in `opt`.
 /// - quest(s)/monster(s)/weapon(s): as specified in `choice`.
 fn exclude(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request> {
+    // Parse before spawning, so that invalid input is reported instead of a timeout.
+    let selection = Selection::parse(choice, arg)?;
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conf = Arc::clone(&*CONFIG);
@@ -407,57 +1154,16 @@ fn exclude(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut config) = conf.try_lock() {
-                match opt {
-                    Options::Set => match choice {
-                        Choices::Quest => {
-                            let quests = arg.smart_cast::<QuestID>()?;
-                            config.settings.excluded.quest = quests;
-                        }
-                        Choices::Monster => {
-                            let monsters = arg.smart_cast::<Monster>()?;
-                            config.settings.excluded.monster = monsters;
-                        }
-                        Choices::Weapon => {
-                            let weapons = arg.smart_cast::<Weapon>()?;
-                            config.settings.excluded.weapon = weapons;
-                        }
-                    },
-                    Options::Add => match choice {
-                        Choices::Quest => {
-                            for quest in arg.smart_cast::<QuestID>()? {
-                                config.settings.excluded.quest.insert(quest);
-                            }
-                        }
-                        Choices::Monster => {
-                            for monster in arg.smart_cast::<Monster>()? {
-                                config.settings.excluded.monster.insert(monster);
-                            }
-                        }
-                        Choices::Weapon => {
-                            for weapon in arg.smart_cast::<Weapon>()? {
-                                config.settings.excluded.weapon.insert(weapon);
-                            }
-                        }
-                    },
-                    Options::Remove => match choice {
-                        Choices::Quest => {
-                            for quest in arg.smart_cast::<QuestID>()? {
-                                config.settings.excluded.quest.remove(&quest);
-                            }
-                        }
-                        Choices::Monster => {
-                            for monster in arg.smart_cast::<Monster>()? {
-                                config.settings.excluded.monster.remove(&monster);
-                            }
-                        }
-                        Choices::Weapon => {
-                            for weapon in arg.smart_cast::<Weapon>()? {
-                                config.settings.excluded.weapon.remove(&weapon);
-                            }
-                        }
-                    },
-                }
                 let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    break Ok(());
+                }
+                let excluded = &mut config.settings.excluded;
+                match &selection {
+                    Selection::Quests(quests) => update(&mut excluded.quest, quests, opt),
+                    Selection::Monsters(monsters) => update(&mut excluded.monster, monsters, opt),
+                    Selection::Weapons(weapons) => update(&mut excluded.weapon, weapons, opt),
+                }
                 *status = JobStatus::ExitSuccess;
                 cvar.notify_one();
                 break Ok(());
@@ -466,8 +1172,8 @@ fn exclude(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(lock.lock().unwrap(), Duration::from_millis(100), |status| {
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
             *status == JobStatus::Pending
         })
         .unwrap();
@@ -476,11 +1182,12 @@ fn exclude(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request
             handle.join().unwrap()?;
             break;
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "settings exclude".to_string(),
-                    wait_for: Duration::from_millis(100),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
@@ -496,6 +1203,8 @@ fn exclude(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request
 }
 
 fn target(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request> {
+    // Parse before spawning, so that invalid input is reported instead of a timeout.
+    let selection = Selection::parse(choice, arg)?;
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conf = Arc::clone(&*CONFIG);
@@ -503,57 +1212,16 @@ fn target(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request>
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut config) = conf.try_lock() {
-                match opt {
-                    Options::Set => match choice {
-                        Choices::Quest => {
-                            let quests = arg.smart_cast::<QuestID>()?;
-                            config.settings.target.quest = quests;
-                        }
-                        Choices::Monster => {
-                            let monsters = arg.smart_cast::<Monster>()?;
-                            config.settings.target.monster = monsters;
-                        }
-                        Choices::Weapon => {
-                            let weapons = arg.smart_cast::<Weapon>()?;
-                            config.settings.target.weapon = weapons;
-                        }
-                    },
-                    Options::Add => match choice {
-                        Choices::Quest => {
-                            for quest in arg.smart_cast::<QuestID>()? {
-                                config.settings.target.quest.insert(quest);
-                            }
-                        }
-                        Choices::Monster => {
-                            for monster in arg.smart_cast::<Monster>()? {
-                                config.settings.target.monster.insert(monster);
-                            }
-                        }
-                        Choices::Weapon => {
-                            for weapon in arg.smart_cast::<Weapon>()? {
-                                config.settings.target.weapon.insert(weapon);
-                            }
-                        }
-                    },
-                    Options::Remove => match choice {
-                        Choices::Quest => {
-                            for quest in arg.smart_cast::<QuestID>()? {
-                                config.settings.target.quest.remove(&quest);
-                            }
-                        }
-                        Choices::Monster => {
-                            for monster in arg.smart_cast::<Monster>()? {
-                                config.settings.target.monster.remove(&monster);
-                            }
-                        }
-                        Choices::Weapon => {
-                            for weapon in arg.smart_cast::<Weapon>()? {
-                                config.settings.target.weapon.remove(&weapon);
-                            }
-                        }
-                    },
-                }
                 let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    break Ok(());
+                }
+                let target = &mut config.settings.target;
+                match &selection {
+                    Selection::Quests(quests) => update(&mut target.quest, quests, opt),
+                    Selection::Monsters(monsters) => update(&mut target.monster, monsters, opt),
+                    Selection::Weapons(weapons) => update(&mut target.weapon, weapons, opt),
+                }
                 *status = JobStatus::ExitSuccess;
                 cvar.notify_one();
                 break Ok(());
@@ -562,8 +1230,8 @@ fn target(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request>
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(lock.lock().unwrap(), Duration::from_millis(100), |status| {
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
             *status == JobStatus::Pending
         })
         .unwrap();
@@ -572,11 +1240,12 @@ fn target(opt: Options, choice: Choices, arg: String) -> anyhow::Result<Request>
             handle.join().unwrap()?;
             break;
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "settings target".to_string(),
-                    wait_for: Duration::from_millis(100),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
@@ -599,6 +1268,10 @@ fn obliterate(choice: Choices) -> anyhow::Result<Request> {
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut config) = conf.try_lock() {
+                let mut status = lock.lock().unwrap();
+                if *status == JobStatus::Cancelled {
+                    break;
+                }
                 match choice {
                     Choices::Quest => {
                         config.settings.target.quest.clear();
@@ -613,7 +1286,6 @@ fn obliterate(choice: Choices) -> anyhow::Result<Request> {
                         config.settings.target.weapon.clear();
                     }
                 }
-                let mut status = lock.lock().unwrap();
                 *status = JobStatus::ExitSuccess;
                 cvar.notify_one();
                 break;
@@ -622,8 +1294,8 @@ fn obliterate(choice: Choices) -> anyhow::Result<Request> {
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(lock.lock().unwrap(), Duration::from_millis(100), |status| {
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
             *status == JobStatus::Pending
         })
         .unwrap();
@@ -631,11 +1303,12 @@ fn obliterate(choice: Choices) -> anyhow::Result<Request> {
         if result.0.ne(&JobStatus::Pending) {
             break;
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
                     command: "settings obliterate".to_string(),
-                    wait_for: Duration::from_millis(100),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
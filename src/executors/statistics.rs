@@ -18,42 +18,106 @@
  */
 
 use boolinator::Boolinator;
-use chrono::DateTime;
+use chrono::{DateTime, Datelike, Duration, NaiveDate};
 use indoc::indoc;
 use itertools::Itertools;
-use serenity::model::user::User;
+use serenity::model::{id::UserId, user::User};
+use sqlite::Connection;
 use std::{
+    collections::HashMap,
     fmt::Debug,
     sync::{Arc, Condvar, Mutex},
     thread,
-    time::Duration,
 };
 use strum::IntoEnumIterator;
 
-use super::utility::JobStatus;
+use super::{
+    activity::activity,
+    chart::{bar_chart, radar_chart},
+    coverage::card,
+    export::export,
+    ranking::ranking,
+    targets,
+    time_zone::{local_datetime, time_zone, today, Zone},
+    utility::{failed_to_aggregate, JobStatus, TIME_LIMIT},
+};
 use crate::{
     data::Weapon,
     error::{CommandError, LogicError, QueryError},
     global::CONN,
     model::{
-        request::{Message, Request},
-        response::{Response, StatisticsSubCommands},
+        request::{Attachment, Message, Request},
+        response::{ChartKind, CoverageBasis, Invoker, Response, StatisticsSubCommands},
         translate::TranslateTo,
     },
 };
 use anyhow::Context;
+use lazy_regex::regex_captures;
 use roulette_macros::{bailout, pretty_info};
 use serenity::{builder::CreateEmbed, utils::Colour};
 
-pub fn statistics(items: &[(String, Response)]) -> anyhow::Result<Request> {
+/// File name of the chart attached to `statistics query`
+const CHART_FILENAME: &str = "statistics.png";
+
+/// Periods are interpreted in the time zone of the invoker's guild.
+pub fn statistics(items: &[(String, Response)], invoker: &Invoker) -> anyhow::Result<Request> {
     match items.translate_to::<StatisticsSubCommands>()? {
-        StatisticsSubCommands::Help => Ok(help()?),
+        StatisticsSubCommands::Help => Ok(help()?.ephemeral()),
         StatisticsSubCommands::Query {
             from,
             weapon,
             since,
             until,
-        } => query(from, weapon, since, until),
+            chart,
+        } => query(
+            from,
+            weapon,
+            since,
+            until,
+            chart.unwrap_or(ChartKind::Bar),
+            time_zone(invoker.guild_id),
+        ),
+        StatisticsSubCommands::Ranking {
+            by,
+            weapon,
+            since,
+            until,
+        } => ranking(by, weapon, since, until, invoker),
+        StatisticsSubCommands::Monsters { user, since, until } => {
+            targets::monsters(user, since, until, invoker)
+        }
+        StatisticsSubCommands::Quests { since, until } => targets::quests(since, until, invoker),
+        StatisticsSubCommands::Coverage {
+            user,
+            basis,
+            restrictions,
+            monsters,
+        } => card(
+            user.unwrap_or_else(|| invoker.user.clone()),
+            basis.unwrap_or(CoverageBasis::Cleared),
+            restrictions,
+            monsters,
+        ),
+        StatisticsSubCommands::Export {
+            user,
+            format,
+            weapon,
+            since,
+            until,
+        } => export(user, format, weapon, since, until, invoker),
+        StatisticsSubCommands::Compare {
+            a,
+            b,
+            weapon,
+            since,
+            until,
+        } => compare(a, b, weapon, since, until, time_zone(invoker.guild_id)),
+        StatisticsSubCommands::Activity {
+            user,
+            per,
+            since,
+            until,
+        } => activity(user, per, since, until, invoker),
     }
 }
 
@@ -65,7 +129,40 @@ fn help() -> anyhow::Result<Request, !> {
         .description("You can find out how many times a hunter has used a certain weapon type.")
         .field(
             "Usage:",
-            "statistics <user> [weapon_keys] [since] [until]",
+            "statistics <user> [weapon_keys] [since] [until] [bar/radar/fields]",
+            false,
+        )
+        .field(
+            "Comparison:",
+            "statistics compare <a> <b> [weapon_keys] [since] [until]",
+            false,
+        )
+        .field(
+            "Targets:",
+            indoc! {"
+                statistics monsters [user] [since] [until]
+                statistics quests [since] [until]
+            "},
+            false,
+        )
+        .field(
+            "Coverage card:",
+            "statistics coverage [user] [assigned/cleared] [restrictions] [monsters]",
+            false,
+        )
+        .field(
+            "Export:",
+            "statistics export <csv/json> [user] [weapon_keys] [since] [until]",
+            false,
+        )
+        .field(
+            "Ranking:",
+            "statistics ranking <rolls/weapon/clears/variety> [weapon_key] [since] [until]",
+            false,
+        )
+        .field(
+            "Activity:",
+            "statistics activity <day/week/month> [user] [since] [until]",
             false,
         )
         .field(
@@ -78,30 +175,113 @@ fn help() -> anyhow::Result<Request, !> {
         )
         .field(
             "since:",
-            "YYYY-MM-DD: Beginning of the period to be covered.",
+            "YYYY-MM-DD or a relative period: Beginning of the period to be covered.",
             true,
         )
         .field(
             "until:",
-            "YYYY-MM-DD: End of the period to be covered.",
+            "YYYY-MM-DD or a relative period: End of the period to be covered.",
             true,
+        )
+        .field(
+            "relative periods:",
+            indoc! {"
+                `today`, `yesterday`, `last 7 days`, `this week`, `last week`,
+                `this month`, `last month`, `this year`
+                (as `since`, they limit both ends of the period)
+                Dates follow the time zone of this server (`/settings timezone`).
+            "},
+            false,
         );
     Ok(Request::Message(Message::Embed(embed)))
 }
 
-fn valid_date(date: &str, param: &str) -> anyhow::Result<String> {
-    Ok(DateTime::parse_from_rfc3339(date)
-        .map_err(|err| QueryError::InvalidDate {
-            param: param.to_string(),
-            actual: date.to_string(),
-            source: err,
-        })?
-        .date()
-        .format("%Y-%m-%d")
-        .to_string())
+/// Inclusive range of dates, unbounded if `None`.
+pub(crate) type Period = (Option<NaiveDate>, Option<NaiveDate>);
+
+/// Parses a date or a relative range into inclusive bounds.
+///
+/// - `YYYY-MM-DD` or RFC 3339: the day
+/// - `today`, `yesterday`
+/// - `last N days` (or `past N days`): N days up to today
+/// - `this week`, `last week` (weeks start on Monday)
+/// - `this month`, `last month`, `this year`
+///
+/// Returns `None` for unknown expressions, and a flag that is true for relative ranges.
+fn parse_range(expr: &str, today: NaiveDate) -> Option<((NaiveDate, NaiveDate), bool)> {
+    let expr = expr.trim().to_lowercase();
+    if let Ok(date) = NaiveDate::parse_from_str(&expr, "%Y-%m-%d") {
+        return Some(((date, date), false));
+    }
+    if let Ok(date) = DateTime::parse_from_rfc3339(&expr.to_uppercase()) {
+        let date = date.naive_local().date();
+        return Some(((date, date), false));
+    }
+    let this_week = today - Duration::days(today.weekday().num_days_from_monday() as i64);
+    let this_month = today.with_day(1)?;
+    let range = match &expr[..] {
+        "today" => (today, today),
+        "yesterday" => (today.pred(), today.pred()),
+        "this week" => (this_week, today),
+        "last week" => (this_week - Duration::days(7), this_week.pred()),
+        "this month" => (this_month, today),
+        "last month" => (this_month.pred().with_day(1)?, this_month.pred()),
+        "this year" => (today.with_ordinal(1)?, today),
+        _ => {
+            let (_, days) = regex_captures!(r"^(?:last|past) ([0-9]+) days?$", &expr)?;
+            let days = days.parse::<i64>().ok().filter(|days| *days > 0)?;
+            (today - Duration::days(days - 1), today)
+        }
+    };
+    Some((range, true))
+}
+
+/// Resolves `since` and `until` into a period.
+///
+/// A relative range given as `since` without `until` limits both ends (e.g. `last month`),
+/// while a plain date limits only the beginning.
+pub(crate) fn valid_period(
+    since: Option<String>,
+    until: Option<String>,
+    today: NaiveDate,
+) -> anyhow::Result<Period> {
+    let parse = |expr: &str, param: &str| {
+        parse_range(expr, today).ok_or_else(|| {
+            anyhow::Error::from(QueryError::InvalidPeriod {
+                param: param.to_string(),
+                actual: expr.to_string(),
+            })
+        })
+    };
+    let since_range = since
+        .as_deref()
+        .map(|since| parse(since, "since"))
+        .transpose()?;
+    let until_range = until
+        .as_deref()
+        .map(|until| parse(until, "until"))
+        .transpose()?;
+    let period = match (since_range, until_range) {
+        (Some(((begin, end), relative)), None) => (Some(begin), relative.as_some(end)),
+        (Some(((begin, _), _)), Some(((_, end), _))) => (Some(begin), Some(end)),
+        (None, Some(((_, end), _))) => (None, Some(end)),
+        (None, None) => (None, None),
+    };
+    if let (Some(begin), Some(end)) = period {
+        if begin > end {
+            bailout!(
+                "Reversed period",
+                QueryError::InvalidPeriod {
+                    param: "since/until".to_string(),
+                    actual: format!("{begin} > {end}"),
+                }
+            );
+        }
+    }
+    Ok(period)
 }
 
-fn valid_weapon(columns: &str) -> anyhow::Result<String> {
+pub(crate) fn valid_weapon(columns: &str) -> anyhow::Result<Vec<String>> {
     let columns = columns.split(',').map(|column| column.trim()).collect_vec();
     let weapons: Vec<&'static str> = Weapon::iter()
         .map(|weapon| {
@@ -124,7 +304,6 @@ fn valid_weapon(columns: &str) -> anyhow::Result<String> {
                 .with_context(|| anyhow::anyhow!("validation error."))
         })
         .collect::<anyhow::Result<Vec<_>>>()
-        .map(|weapons| weapons.join(", "))
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -235,107 +414,177 @@ fn query(
     weapon: Option<String>,
     since: Option<String>,
     until: Option<String>,
+    chart: ChartKind,
+    tz: Zone,
 ) -> anyhow::Result<Request> {
+    let stats = aggregate(vec![user.id], weapon, since, until, tz, "statistics query")?;
+    let fields = stats
+        .into_iter()
+        .flatten()
+        .map(|stat| stat.into_field())
+        .collect_vec();
+    let bars = fields
+        .iter()
+        .map(|(name, count, _)| (*name, *count))
+        .collect_vec();
+    let rendered = match chart {
+        _ if bars.is_empty() => None,
+        ChartKind::Bar => Some(bar_chart(&user.name, &bars)),
+        ChartKind::Radar => Some(radar_chart(&user.name, &bars)),
+        ChartKind::Fields => None,
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed.title(&user.name);
+    match rendered {
+        Some(Ok(png)) => {
+            embed
+                .colour(Colour::MEIBE_PINK)
+                .image(format!("attachment://{CHART_FILENAME}"));
+            Ok(Request::Attachments {
+                message: Message::Embed(embed),
+                files: vec![Attachment {
+                    filename: CHART_FILENAME.to_string(),
+                    data: png,
+                }],
+            })
+        }
+        rendered => {
+            // Fall back to the embed fields if the chart could not be rendered.
+            if let Some(Err(err)) = rendered {
+                tracing::warn!("failed to render a chart: {err:?}");
+            }
+            embed.fields(fields);
+            Ok(Request::Message(Message::Embed(embed)))
+        }
+    }
+}
+
+/// Shows per-weapon counts of two hunters side by side.
+#[tracing::instrument]
+fn compare(
+    a: User,
+    b: User,
+    weapon: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    tz: Zone,
+) -> anyhow::Result<Request> {
+    let stats = aggregate(
+        vec![a.id, b.id],
+        weapon,
+        since,
+        until,
+        tz,
+        "statistics compare",
+    )?;
+    let counts = stats
+        .into_iter()
+        .map(|stats| {
+            stats
+                .into_iter()
+                .map(|stat| {
+                    let (name, count, _) = stat.into_field();
+                    (name, count)
+                })
+                .collect::<HashMap<_, _>>()
+        })
+        .collect_vec();
+    let (a_counts, b_counts) = match &counts[..] {
+        [a_counts, b_counts] => (a_counts, b_counts),
+        _ => unreachable!("aggregated for two hunters"),
+    };
+    let a_total = a_counts.values().sum::<usize>();
+    let b_total = b_counts.values().sum::<usize>();
+    let share = |count: usize, total: usize| {
+        if total == 0 {
+            0.0
+        } else {
+            count as f64 * 100.0 / total as f64
+        }
+    };
+
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title(format!("{} vs {}", a.name, b.name))
+        .description(format!(
+            "total: {a_total} vs {b_total} ({diff:+})",
+            diff = a_total as i64 - b_total as i64,
+        ))
+        .fields(
+            Weapon::iter()
+                .map(|weapon| weapon.ja())
+                .filter(|name| a_counts.contains_key(name) || b_counts.contains_key(name))
+                .map(|name| {
+                    let a_count = a_counts.get(name).copied().unwrap_or_default();
+                    let b_count = b_counts.get(name).copied().unwrap_or_default();
+                    (
+                        name,
+                        format!(
+                            "{a_count} ({a_share:.1}%) vs {b_count} ({b_share:.1}%)\n{diff:+}",
+                            a_share = share(a_count, a_total),
+                            b_share = share(b_count, b_total),
+                            diff = a_count as i64 - b_count as i64,
+                        ),
+                        true,
+                    )
+                }),
+        );
+    Ok(Request::Message(Message::Embed(embed)))
+}
+
+/// Counts rolls of each hunter in `ids` per weapon, in the same order as `ids`.
+///
+/// `command` names the command in the time limit error.
+fn aggregate(
+    ids: Vec<UserId>,
+    weapon: Option<String>,
+    since: Option<String>,
+    until: Option<String>,
+    tz: Zone,
+    command: &'static str,
+) -> anyhow::Result<Vec<Vec<Stat>>> {
     let pair = Arc::new((Mutex::new(JobStatus::Pending), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let conn = Arc::clone(&*CONN);
 
-    let handle = thread::spawn(move || -> anyhow::Result<Request> {
+    let handle = thread::spawn(move || -> anyhow::Result<Vec<Vec<Stat>>> {
         let (lock, cvar) = &*pair2;
         loop {
             if let Ok(ref mut conn) = conn.try_lock() {
-                let response = (|| -> anyhow::Result<Vec<Stat>> {
-                    let weapon = weapon.map_or_else(
+                let response = (|| -> anyhow::Result<Vec<Vec<Stat>>> {
+                    let weapons = weapon.map_or_else(
                         || {
                             Ok(Weapon::iter()
                                 .map(|weapon| weapon.to_string())
-                                .collect_vec()
-                                .join(", "))
+                                .collect_vec())
                         },
                         |columns| valid_weapon(&columns),
                     )?;
-
-                    let date = match (since, until) {
-                        (Some(begin), Some(end)) => {
-                            let begin = valid_date(&begin, "since")?;
-                            let end = valid_date(&end, "until")?;
-                            Some(format!("date(generated_at) BETWEEN {begin} AND {end}"))
-                        }
-                        (Some(begin), None) => {
-                            let begin = valid_date(&begin, "since")?;
-                            Some(format!("{begin} <= date(generated_at) "))
-                        }
-                        (None, Some(end)) => {
-                            let end = valid_date(&end, "until")?;
-                            Some(format!("date(generated_at) <= {end}"))
-                        }
-                        _ => None,
-                    };
-
-                    let table = if date.is_some() { "logs" } else { "statistics" }.to_string();
-                    let id = user.id.0;
-                    let query = format!(
-                        indoc! {r#"
-                            SELECT {weapon}
-                            FROM {table}
-                            WHERE
-                                id = '{id}'
-                                {date}
-                        "#},
-                        weapon = weapon,
-                        table = table,
-                        id = id,
-                        date = date.unwrap_or_default()
-                    );
-
-                    let mut result = Vec::new();
-                    let query_result = conn.iterate(&query, |pairs| {
-                        for &(column, value) in pairs.iter() {
-                            if let Some(Ok(count)) = value.map(|v| v.parse::<usize>()) {
-                                result.push(column.into_stat_with(count))
-                            }
-                        }
-                        true
-                    });
-
-                    if let Err(err) = query_result {
-                        bailout!(
-                            "query error",
-                            QueryError::FailedToAggregate {
-                                raw: format!("{err}"),
-                                query
-                            }
-                        );
-                    }
-
-                    result.into_iter().collect::<anyhow::Result<Vec<_>>>()
+                    let period = valid_period(since, until, today(tz))?;
+                    ids.iter()
+                        .map(|id| count(conn, id.0, &weapons, period, tz))
+                        .collect()
                 })();
 
                 let mut status = lock.lock().unwrap();
-                if let Err(err) = response {
+                if response.is_err() {
                     *status = JobStatus::ExitFailure;
-                    cvar.notify_one();
-                    return Err(err);
                 } else {
-                    let mut embed = CreateEmbed::default();
-                    embed
-                        .title(user.name)
-                        .fields(response?.into_iter().map(|stat| stat.into_field()));
                     *status = JobStatus::ExitSuccess;
-                    cvar.notify_one();
-                    break Ok(Request::Message(Message::Embed(embed)));
                 }
+                cvar.notify_one();
+                break response;
             }
         }
     });
     // wait for the thread to start up
     let (lock, cvar) = &*pair;
-    let result = cvar
-        .wait_timeout_while(
-            lock.lock().unwrap(),
-            Duration::from_millis(1000),
-            |status| *status == JobStatus::Pending,
-        )
+    let mut result = cvar
+        .wait_timeout_while(lock.lock().unwrap(), TIME_LIMIT, |status| {
+            *status == JobStatus::Pending
+        })
         .unwrap();
     loop {
         if result.0.ne(&JobStatus::Pending) {
@@ -343,13 +592,140 @@ fn query(
                 .join()
                 .expect("Couldn't join on the associated thread");
         } else if result.1.timed_out() {
+            *result.0 = JobStatus::Cancelled;
             bailout!(
                 "TLE",
                 CommandError::TimeLimitExceeded {
-                    command: "statistics query".to_string(),
-                    wait_for: Duration::from_millis(1000),
+                    command: command.to_string(),
+                    wait_for: TIME_LIMIT,
                 }
             );
         }
     }
 }
+
+/// Counts rolls of the hunter per weapon.
+fn count(
+    conn: &Connection,
+    id: u64,
+    weapons: &[String],
+    period: Period,
+    tz: Zone,
+) -> anyhow::Result<Vec<Stat>> {
+    let query = match period {
+        // Without a period, read the running totals.
+        (None, None) => format!(
+            indoc! {r#"
+                SELECT {columns}
+                FROM statistics
+                WHERE id = {id}
+            "#},
+            columns = weapons
+                .iter()
+                .map(|weapon| format!("{weapon:?}"))
+                .join(", "),
+            id = id,
+        ),
+        // Otherwise, count the rolls in the period.
+        (since, until) => format!(
+            indoc! {r#"
+                SELECT weapon, COUNT(*) AS count
+                FROM logs
+                WHERE id = {id}
+                    AND weapon IN ({weapons})
+                    {since}
+                    {until}
+                GROUP BY weapon
+            "#},
+            id = id,
+            weapons = weapons
+                .iter()
+                .map(|weapon| format!("'{weapon}'"))
+                .join(", "),
+            since = since.map_or_else(String::new, |since| format!(
+                "AND date({}) >= '{}'",
+                local_datetime("generated_at", tz),
+                since.format("%Y-%m-%d")
+            )),
+            until = until.map_or_else(String::new, |until| format!(
+                "AND date({}) <= '{}'",
+                local_datetime("generated_at", tz),
+                until.format("%Y-%m-%d")
+            )),
+        ),
+    };
+
+    let mut result = Vec::new();
+    conn.iterate(&query, |pairs| {
+        match pairs {
+            // aggregated from logs
+            [("weapon", Some(weapon)), ("count", Some(count))] => {
+                if let Ok(count) = count.parse::<usize>() {
+                    result.push(weapon.into_stat_with(count))
+                }
+            }
+            // running totals
+            _ => {
+                for &(column, value) in pairs.iter() {
+                    if let Some(Ok(count)) = value.map(|v| v.parse::<usize>()) {
+                        result.push(column.into_stat_with(count))
+                    }
+                }
+            }
+        }
+        true
+    })
+    .map_err(|err| failed_to_aggregate(&query, err))?;
+
+    result.into_iter().collect::<anyhow::Result<Vec<_>>>()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn period_test() {
+        // Wednesday
+        let today = NaiveDate::from_ymd(2021, 6, 16);
+        let day = |m, d| NaiveDate::from_ymd(2021, m, d);
+
+        assert_eq!(
+            valid_period(Some("2021-06-01".to_string()), None, today).unwrap(),
+            (Some(day(6, 1)), None)
+        );
+        assert_eq!(
+            valid_period(
+                Some("2021-06-01T00:00:00+09:00".to_string()),
+                Some("2021-06-10".to_string()),
+                today
+            )
+            .unwrap(),
+            (Some(day(6, 1)), Some(day(6, 10)))
+        );
+        assert_eq!(
+            valid_period(Some("last 7 days".to_string()), None, today).unwrap(),
+            (Some(day(6, 10)), Some(today))
+        );
+        assert_eq!(
+            valid_period(Some("this week".to_string()), None, today).unwrap(),
+            (Some(day(6, 14)), Some(today))
+        );
+        assert_eq!(
+            valid_period(Some("Last Month".to_string()), None, today).unwrap(),
+            (Some(day(5, 1)), Some(day(5, 31)))
+        );
+        assert_eq!(
+            valid_period(None, Some("yesterday".to_string()), today).unwrap(),
+            (None, Some(day(6, 15)))
+        );
+        assert!(valid_period(Some("someday".to_string()), None, today).is_err());
+        assert!(valid_period(Some("last 0 days".to_string()), None, today).is_err());
+        assert!(valid_period(
+            Some("2021-06-10".to_string()),
+            Some("2021-06-01".to_string()),
+            today
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,290 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Monster and quest views of `/statistics`.
+
+use super::{
+    rolls::{self, Target},
+    statistics::{valid_period, Period},
+    time_zone::{local_datetime, time_zone, today, Zone},
+    utility::{field_value, hunter_names},
+};
+use crate::{
+    data::{Monster, QuestID},
+    error::QueryError,
+    global::CONN,
+    model::{
+        request::{Message, Request},
+        response::Invoker,
+    },
+};
+use itertools::Itertools;
+use roulette_macros::bailout;
+use serenity::{builder::CreateEmbed, model::id::UserId, model::user::User, utils::Colour};
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// Entries shown in a top list
+const TOP: usize = 10;
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error(
+        r#"
+        SELECT quest, monster, COUNT(*) AS count FROM rolls
+            WHERE {conditions}
+            GROUP BY quest, monster
+    "#
+    )]
+    RollTargets { conditions: String },
+    #[error(
+        r#"
+        SELECT logs.id AS id, rolls.quest AS quest, rolls.monster AS monster, COUNT(*) AS count
+            FROM logs JOIN rolls ON rolls.id = logs.roll_id
+            WHERE {conditions}
+            GROUP BY logs.id, rolls.quest, rolls.monster
+    "#
+    )]
+    HunterTargets { conditions: String },
+}
+
+/// Number of rolls per target, and per hunter if aggregated from logs.
+#[derive(Debug, Default)]
+struct Counts {
+    quests: HashMap<QuestID, usize>,
+    /// Monsters of rolled quests are counted as well.
+    monsters: HashMap<Monster, usize>,
+}
+
+impl Counts {
+    fn add(&mut self, quest: Option<&str>, monster: Option<&str>, count: usize) {
+        if let Some(target) = Target::from_columns(quest, monster) {
+            if let Target::Quest(id) = target {
+                *self.quests.entry(id).or_default() += count;
+            }
+            for monster in target.monsters() {
+                *self.monsters.entry(monster).or_default() += count;
+            }
+        }
+    }
+}
+
+/// Sorts counts in descending order, ties broken by `key`.
+fn top<T, K: Ord>(counts: &HashMap<T, usize>, key: impl Fn(&T) -> K) -> Vec<(&T, usize)> {
+    counts
+        .iter()
+        .map(|(target, count)| (target, *count))
+        .sorted_by_key(|(target, count)| (std::cmp::Reverse(*count), key(*target)))
+        .collect()
+}
+
+fn conditions(period: Period, tz: Zone, hunter: Option<UserId>) -> String {
+    let mut conditions = vec!["1".to_string()];
+    if let Some(since) = period.0 {
+        conditions.push(format!(
+            "date({}) >= '{since}'",
+            local_datetime("rolls.generated_at", tz)
+        ));
+    }
+    if let Some(until) = period.1 {
+        conditions.push(format!(
+            "date({}) <= '{until}'",
+            local_datetime("rolls.generated_at", tz)
+        ));
+    }
+    if let Some(hunter) = hunter {
+        conditions.push(format!("logs.id = {}", hunter.0));
+    }
+    conditions.join(" AND ")
+}
+
+/// Counts rolls per target over all rolls.
+fn roll_counts(conn: &sqlite::Connection, period: Period, tz: Zone) -> anyhow::Result<Counts> {
+    let query = Query::RollTargets {
+        conditions: conditions(period, tz, None),
+    };
+    let mut counts = Counts::default();
+    if let Err(err) = conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, quest), (_, monster), (_, Some(count))] = pairs {
+            counts.add(*quest, *monster, count.parse().unwrap_or_default());
+        }
+        true
+    }) {
+        bailout!(
+            "query error",
+            QueryError::FailedToAggregate {
+                raw: format!("{err}"),
+                query: format!("{query}"),
+            }
+        );
+    }
+    Ok(counts)
+}
+
+/// Counts rolls per target of each hunter (or only `hunter`).
+fn hunter_counts(
+    conn: &sqlite::Connection,
+    period: Period,
+    tz: Zone,
+    hunter: Option<UserId>,
+) -> anyhow::Result<HashMap<UserId, Counts>> {
+    let query = Query::HunterTargets {
+        conditions: conditions(period, tz, hunter),
+    };
+    let mut counts = HashMap::<UserId, Counts>::new();
+    if let Err(err) = conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, Some(id)), (_, quest), (_, monster), (_, Some(count))] = pairs {
+            if let Ok(id) = id.parse::<u64>() {
+                counts.entry(UserId(id)).or_default().add(
+                    *quest,
+                    *monster,
+                    count.parse().unwrap_or_default(),
+                );
+            }
+        }
+        true
+    }) {
+        bailout!(
+            "query error",
+            QueryError::FailedToAggregate {
+                raw: format!("{err}"),
+                query: format!("{query}"),
+            }
+        );
+    }
+    Ok(counts)
+}
+
+fn period_text(period: Period) -> String {
+    match period {
+        (None, None) => "all time".to_string(),
+        (since, until) => format!(
+            "{} ~ {}",
+            since.map(|date| date.to_string()).unwrap_or_default(),
+            until.map(|date| date.to_string()).unwrap_or_default(),
+        ),
+    }
+}
+
+/// Shows the most rolled monsters, of `user` or of everyone with each hunter's favorite.
+pub(crate) fn monsters(
+    user: Option<User>,
+    since: Option<String>,
+    until: Option<String>,
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
+    let tz = time_zone(invoker.guild_id);
+    let period = valid_period(since, until, today(tz))?;
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .footer(|footer| footer.text(period_text(period)));
+    let conn = CONN.lock().unwrap();
+    rolls::ensure_tables(&conn)?;
+    match user {
+        Some(user) => {
+            let counts = hunter_counts(&conn, period, tz, Some(user.id))?
+                .remove(&user.id)
+                .unwrap_or_default();
+            embed
+                .title(format!("monsters hunted by {}", user.name))
+                .description(field_value(
+                    top(&counts.monsters, |monster| monster.ja())
+                        .into_iter()
+                        .take(TOP)
+                        .map(|(monster, count)| format!("{} — {count}", monster.ja())),
+                    "\n",
+                ));
+        }
+        None => {
+            let counts = roll_counts(&conn, period, tz)?;
+            let hunters = hunter_counts(&conn, period, tz, None)?;
+            let names = hunter_names(&conn, &hunters.keys().copied().collect_vec())?;
+            embed
+                .title("most rolled monsters")
+                .description(field_value(
+                    top(&counts.monsters, |monster| monster.ja())
+                        .into_iter()
+                        .take(TOP)
+                        .map(|(monster, count)| format!("{} — {count}", monster.ja())),
+                    "\n",
+                ))
+                .field(
+                    "most hunted by each hunter",
+                    field_value(
+                        hunters
+                            .iter()
+                            .filter_map(|(id, counts)| {
+                                let (monster, count) =
+                                    top(&counts.monsters, |monster| monster.ja())
+                                        .into_iter()
+                                        .next()?;
+                                let name = names.get(id).cloned().unwrap_or_else(|| id.to_string());
+                                Some((name, monster.ja(), count))
+                            })
+                            .sorted()
+                            .map(|(name, monster, count)| format!("{name}: {monster} ({count})")),
+                        "\n",
+                    ),
+                    false,
+                );
+        }
+    }
+    Ok(Request::Message(Message::Embed(embed)))
+}
+
+/// Shows the most rolled quests and quests that have never been rolled.
+pub(crate) fn quests(
+    since: Option<String>,
+    until: Option<String>,
+    invoker: &Invoker,
+) -> anyhow::Result<Request> {
+    let tz = time_zone(invoker.guild_id);
+    let period = valid_period(since, until, today(tz))?;
+    let counts = {
+        let conn = CONN.lock().unwrap();
+        rolls::ensure_tables(&conn)?;
+        roll_counts(&conn, period, tz)?
+    };
+    let never = QuestID::all()
+        .filter(|id| !counts.quests.contains_key(id))
+        .collect_vec();
+    let mut embed = CreateEmbed::default();
+    embed
+        .colour(Colour::MEIBE_PINK)
+        .title("most rolled quests")
+        .description(field_value(
+            top(&counts.quests, |id| *id)
+                .into_iter()
+                .take(TOP)
+                .map(|(id, count)| {
+                    format!(
+                        "`{id}` {title} — {count}",
+                        title = id.quest().map(|quest| quest.title()).unwrap_or_default(),
+                    )
+                }),
+            "\n",
+        ))
+        .field(
+            format!("never rolled ({})", never.len()),
+            field_value(never.iter().map(|id| format!("`{id}`")), " "),
+            false,
+        )
+        .footer(|footer| footer.text(period_text(period)));
+    Ok(Request::Message(Message::Embed(embed)))
+}
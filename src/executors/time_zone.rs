@@ -0,0 +1,247 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Per-guild time zones.
+//!
+//! Timestamps in the database are written by SQLite's `datetime('now', 'localtime')`,
+//! i.e. in the local time of the host running the bot.
+//! Helpers in this module convert them into the time zone of a guild.
+
+use crate::{global::CONFIG, model::response::Bucket};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+use chrono_tz::Tz;
+use serenity::model::id::GuildId;
+
+/// Time zone to filter, bucket and show timestamps in.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Zone {
+    /// Local time of the host running the bot, for guilds which have not set a time zone
+    Host,
+    /// Time zone set by `/settings timezone`
+    Named(Tz),
+}
+
+impl Zone {
+    pub(crate) fn name(&self) -> &str {
+        match self {
+            Zone::Host => "host local time",
+            Zone::Named(tz) => tz.name(),
+        }
+    }
+
+    /// Formats a UTC instant in the zone.
+    pub(crate) fn format(&self, utc: &NaiveDateTime, fmt: &str) -> String {
+        match self {
+            Zone::Host => Local.from_utc_datetime(utc).format(fmt).to_string(),
+            Zone::Named(tz) => tz.from_utc_datetime(utc).format(fmt).to_string(),
+        }
+    }
+
+    /// Formats a UTC instant in the zone as RFC 3339.
+    pub(crate) fn to_rfc3339(self, utc: &NaiveDateTime) -> String {
+        match self {
+            Zone::Host => Local.from_utc_datetime(utc).to_rfc3339(),
+            Zone::Named(tz) => tz.from_utc_datetime(utc).to_rfc3339(),
+        }
+    }
+}
+
+/// Returns the time zone set for the guild, or [Zone::Host].
+pub(crate) fn time_zone(guild_id: Option<GuildId>) -> Zone {
+    let config = CONFIG.lock().unwrap();
+    guild_id
+        .and_then(|guild_id| config.time_zones.get(&guild_id.to_string()))
+        .and_then(|name| name.parse::<Tz>().ok())
+        .map_or(Zone::Host, Zone::Named)
+}
+
+/// Today in the time zone.
+pub(crate) fn today(zone: Zone) -> NaiveDate {
+    match zone {
+        Zone::Host => Local::now().date().naive_local(),
+        Zone::Named(tz) => Utc::now().with_timezone(&tz).date().naive_local(),
+    }
+}
+
+/// First day to look for UTC offset transitions from; no roll is older than it.
+const TRANSITIONS_SINCE: (i32, u32, u32) = (2021, 1, 1);
+
+/// SQLite expression converting `column`, stored in the host's local time,
+/// into the local time of `zone`.
+///
+/// Each row is shifted by the UTC offset of `zone` at its own instant,
+/// so rows on both sides of a daylight saving transition are converted correctly.
+/// Wrap it with `date()` or `strftime()` to filter rows by local days, or see [bucket].
+pub(crate) fn local_datetime(column: &str, zone: Zone) -> String {
+    let tz = match zone {
+        Zone::Host => return format!("datetime({column})"),
+        Zone::Named(tz) => tz,
+    };
+    let (y, m, d) = TRANSITIONS_SINCE;
+    let since = NaiveDate::from_ymd(y, m, d).and_hms(0, 0, 0);
+    let until = Utc::now().naive_utc() + Duration::days(366);
+    let utc = format!("datetime({column}, 'utc')");
+    let modifier = |offset: i32| format!("'{offset:+} seconds'");
+    let (first, transitions) = offsets(tz, since, until);
+    let offset = match transitions.last() {
+        None => modifier(first),
+        Some((_, last)) => {
+            // The offset before each transition applies to the rows until it.
+            let befores =
+                std::iter::once(first).chain(transitions.iter().map(|(_, offset)| *offset));
+            let cases = transitions
+                .iter()
+                .zip(befores)
+                .map(|((at, _), before)| {
+                    format!(
+                        "WHEN {utc} < '{at}' THEN {before}",
+                        at = at.format("%Y-%m-%d %H:%M:%S"),
+                        before = modifier(before),
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join(" ");
+            format!("CASE {cases} ELSE {} END", modifier(*last))
+        }
+    };
+    format!("datetime({utc}, {offset})")
+}
+
+/// UTC offset of `tz` at `since` in seconds, and the instants in `[since, until)`
+/// from which it changes, with the new offsets.
+fn offsets(tz: Tz, since: NaiveDateTime, until: NaiveDateTime) -> (i32, Vec<(NaiveDateTime, i32)>) {
+    let offset = |utc: &NaiveDateTime| tz.offset_from_utc_datetime(utc).fix().local_minus_utc();
+    let first = offset(&since);
+    let mut transitions = vec![];
+    let (mut before, mut current) = (since, first);
+    while before < until {
+        let after = before + Duration::days(1);
+        if offset(&after) != current {
+            // Narrow down the first second with the new offset.
+            let (mut lower, mut upper) = (before, after);
+            while upper - lower > Duration::seconds(1) {
+                let middle = lower + Duration::seconds((upper - lower).num_seconds() / 2);
+                if offset(&middle) == current {
+                    lower = middle;
+                } else {
+                    upper = middle;
+                }
+            }
+            current = offset(&upper);
+            transitions.push((upper, current));
+        }
+        before = after;
+    }
+    (first, transitions)
+}
+
+/// SQLite expression of the local day, week or month of `column` in `zone` (see [local_datetime]).
+///
+/// Days are written as `YYYY-MM-DD`, weeks as their Monday and months as `YYYY-MM`,
+/// so buckets sort in time order.
+pub(crate) fn bucket(column: &str, zone: Zone, per: Bucket) -> String {
+    let local = local_datetime(column, zone);
+    match per {
+        Bucket::Day => format!("date({local})"),
+        Bucket::Week => format!("date({local}, 'weekday 0', '-6 days')"),
+        Bucket::Month => format!("strftime('%Y-%m', {local})"),
+    }
+}
+
+/// Formats a UTC timestamp read from SQLite (`YYYY-MM-DD HH:MM:SS`) in `zone`.
+///
+/// Convert stored timestamps with `datetime(column, 'utc')` before reading them.
+///
+/// Unparsable timestamps are returned as is.
+pub(crate) fn format_timestamp(utc: &str, zone: Zone) -> String {
+    NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S")
+        .map(|utc| zone.format(&utc, "%Y-%m-%d %H:%M %Z"))
+        .unwrap_or_else(|_| utc.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Evaluates a SQLite expression of `column` for a timestamp stored in the host's local time.
+    fn eval(expression: impl Fn(&str) -> String, stored: &str) -> String {
+        let conn = sqlite::open(":memory:").unwrap();
+        let mut result = String::new();
+        conn.iterate(
+            format!("SELECT {}", expression(&format!("'{stored}'"))),
+            |pairs| {
+                result = pairs[0].1.unwrap_or_default().to_string();
+                true
+            },
+        )
+        .unwrap();
+        result
+    }
+
+    #[test]
+    fn local_datetime_test() {
+        let tz = chrono_tz::America::New_York;
+        // Winter and summer rows are shifted by their own offsets.
+        for utc in ["2022-01-15 12:00:00", "2022-07-15 12:00:00"] {
+            let utc = NaiveDateTime::parse_from_str(utc, "%Y-%m-%d %H:%M:%S").unwrap();
+            let stored = Local.from_utc_datetime(&utc).format("%Y-%m-%d %H:%M:%S");
+            assert_eq!(
+                eval(
+                    |column| local_datetime(column, Zone::Named(tz)),
+                    &stored.to_string()
+                ),
+                tz.from_utc_datetime(&utc)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+            );
+        }
+        assert_eq!(
+            eval(
+                |column| local_datetime(column, Zone::Host),
+                "2022-07-15 12:00:00"
+            ),
+            "2022-07-15 12:00:00"
+        );
+    }
+
+    #[test]
+    fn offsets_test() {
+        let since = NaiveDate::from_ymd(2022, 1, 1).and_hms(0, 0, 0);
+        let until = NaiveDate::from_ymd(2023, 1, 1).and_hms(0, 0, 0);
+        let (first, transitions) = offsets(chrono_tz::America::New_York, since, until);
+        assert_eq!(first, -5 * 3600);
+        assert_eq!(
+            transitions,
+            vec![
+                (NaiveDate::from_ymd(2022, 3, 13).and_hms(7, 0, 0), -4 * 3600),
+                (NaiveDate::from_ymd(2022, 11, 6).and_hms(6, 0, 0), -5 * 3600),
+            ]
+        );
+        assert!(offsets(chrono_tz::Asia::Tokyo, since, until).1.is_empty());
+    }
+
+    #[test]
+    fn bucket_test() {
+        // 2022-07-17 is a Sunday.
+        let stored = "2022-07-17 12:00:00";
+        let bucket = |per| eval(|column| bucket(column, Zone::Host, per), stored);
+        assert_eq!(bucket(Bucket::Day), "2022-07-17");
+        assert_eq!(bucket(Bucket::Week), "2022-07-11");
+        assert_eq!(bucket(Bucket::Month), "2022-07");
+    }
+}
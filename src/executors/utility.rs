@@ -17,11 +17,30 @@
  *
  */
 
+use crate::{error::QueryError, global::HTTP};
+use anyhow::Context;
+use itertools::Itertools;
+use serenity::{
+    http::Http,
+    model::{
+        id::{GuildId, RoleId, UserId},
+        user::User,
+    },
+};
+use sqlite::Connection;
+use std::{collections::HashMap, fmt::Display, future::Future, time::Duration};
+use thiserror::Error;
+
+/// Time limit for executors waiting on shared resources (CONFIG, CONN).
+pub(crate) const TIME_LIMIT: Duration = Duration::from_millis(1000);
+
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
 pub(crate) enum JobStatus {
     Pending,
     ExitSuccess,
     ExitFailure,
+    /// The caller has exceeded [TIME_LIMIT], so the worker must not change anything.
+    Cancelled,
 }
 
 pub struct ExitGuard {
@@ -33,3 +52,117 @@ impl Drop for ExitGuard {
         (self.finally)();
     }
 }
+
+/// Joins `items` with `separator` into an embed field value, which is limited to 1024 characters.
+pub(crate) fn field_value(items: impl IntoIterator<Item = String>, separator: &str) -> String {
+    let value = items.into_iter().join(separator);
+    if value.is_empty() {
+        "(none)".to_string()
+    } else if value.chars().count() > 1000 {
+        format!("{}\n...", value.chars().take(1000).collect::<String>())
+    } else {
+        value
+    }
+}
+
+/// Wraps an error of `query` which modifies the database.
+pub(crate) fn failed_to_store(query: impl Display, err: sqlite::Error) -> anyhow::Error {
+    anyhow::Error::from(QueryError::FailedToStore {
+        raw: format!("{err}"),
+        query: format!("{query}"),
+    })
+    .context("Query failed.")
+}
+
+/// Wraps an error of `query` which reads the database.
+pub(crate) fn failed_to_aggregate(query: impl Display, err: sqlite::Error) -> anyhow::Error {
+    anyhow::Error::from(QueryError::FailedToAggregate {
+        raw: format!("{err}"),
+        query: format!("{query}"),
+    })
+    .context("Query failed.")
+}
+
+#[derive(Debug, Error)]
+enum Query {
+    #[error("SELECT id, name FROM hunters WHERE id IN ({ids})")]
+    SelectNames { ids: String },
+}
+
+/// Resolves display names of `ids` from the hunters table.
+///
+/// Users who have never been upserted into the table are absent from the result.
+pub(crate) fn hunter_names(
+    conn: &Connection,
+    ids: &[UserId],
+) -> anyhow::Result<HashMap<UserId, String>> {
+    let mut names = HashMap::new();
+    if ids.is_empty() {
+        return Ok(names);
+    }
+    let query = Query::SelectNames {
+        ids: ids
+            .iter()
+            .map(|id| id.0.to_string())
+            .collect::<Vec<_>>()
+            .join(", "),
+    };
+    conn.iterate(format!("{query}"), |pairs| {
+        if let [(_, Some(id)), (_, Some(name))] = pairs {
+            if let Ok(id) = id.parse::<u64>() {
+                names.insert(UserId(id), name.to_string());
+            }
+        }
+        true
+    })
+    .map_err(|err| failed_to_aggregate(&query, err))?;
+    Ok(names)
+}
+
+/// Runs a request to the Discord API from an executor.
+///
+/// Executors run on blocking threads of the runtime, so they can wait for the response.
+fn discord<F, Fut, T>(request: F) -> anyhow::Result<T>
+where
+    F: FnOnce(&'static Http) -> Fut,
+    Fut: Future<Output = serenity::Result<T>>,
+{
+    let http = HTTP
+        .get()
+        .with_context(|| anyhow::anyhow!("the BOT is not ready"))?;
+    tokio::runtime::Handle::current()
+        .block_on(request(http.as_ref()))
+        .with_context(|| anyhow::anyhow!("http error"))
+}
+
+/// Fetches users of `ids`, leaving out bots as [role_members] does.
+pub(crate) fn fetch_users(ids: &[UserId]) -> anyhow::Result<Vec<User>> {
+    let users = ids
+        .iter()
+        .map(|id| discord(|http| http.get_user(id.0)))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    Ok(users.into_iter().filter(|user| !user.bot).collect())
+}
+
+/// Fetches all members of the guild with the role.
+///
+/// Listing guild members requires the privileged "Server Members" intent to be enabled.
+pub(crate) fn role_members(guild_id: GuildId, role_id: RoleId) -> anyhow::Result<Vec<User>> {
+    const LIMIT: u64 = 1000;
+    let mut users = vec![];
+    let mut after = None;
+    loop {
+        let members = discord(|http| http.get_guild_members(guild_id.0, Some(LIMIT), after))?;
+        after = members.last().map(|member| member.user.id.0);
+        let len = members.len() as u64;
+        users.extend(
+            members
+                .into_iter()
+                .filter(|member| member.roles.contains(&role_id) && !member.user.bot)
+                .map(|member| member.user),
+        );
+        if len < LIMIT {
+            break Ok(users);
+        }
+    }
+}
@@ -0,0 +1,90 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+//! Weighted draws of `/generate` candidates (see [crate::data::Weights]).
+
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+
+/// Draws one of `candidates` in proportion to `weight`, or `None` if there is no candidate.
+pub(crate) fn draw<T, F, R>(candidates: &[T], weight: F, rng: &mut R) -> Option<T>
+where
+    T: Copy,
+    F: Fn(T) -> f64,
+    R: Rng + ?Sized,
+{
+    let index = WeightedIndex::new(candidates.iter().map(|candidate| weight(*candidate))).ok()?;
+    Some(candidates[index.sample(rng)])
+}
+
+/// Probability of drawing each of `candidates` with [draw].
+pub(crate) fn probabilities<T, F>(candidates: &[T], weight: F) -> Vec<(T, f64)>
+where
+    T: Copy,
+    F: Fn(T) -> f64,
+{
+    let total: f64 = candidates.iter().map(|candidate| weight(*candidate)).sum();
+    candidates
+        .iter()
+        .map(|candidate| {
+            (
+                *candidate,
+                if total > 0.0 {
+                    weight(*candidate) / total
+                } else {
+                    0.0
+                },
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn weight(candidate: char) -> f64 {
+        match candidate {
+            'a' => 3.0,
+            'b' => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    #[test]
+    fn draw_test() {
+        let mut rng = rand::thread_rng();
+        assert_eq!(draw(&[], weight, &mut rng), None);
+        assert_eq!(draw(&['a'], weight, &mut rng), Some('a'));
+        // Candidates weighing zero are never drawn.
+        assert!((0..100).all(|_| draw(&['a', 'z'], weight, &mut rng) == Some('a')));
+        assert_eq!(draw(&['y', 'z'], weight, &mut rng), None);
+    }
+
+    #[test]
+    fn probabilities_test() {
+        assert_eq!(probabilities(&['a'], weight), vec![('a', 1.0)]);
+        assert_eq!(
+            probabilities(&['a', 'b', 'z'], weight),
+            vec![('a', 0.75), ('b', 0.25), ('z', 0.0)]
+        );
+        assert_eq!(
+            probabilities(&['y', 'z'], weight),
+            vec![('y', 0.0), ('z', 0.0)]
+        );
+    }
+}
@@ -19,15 +19,18 @@
 
 use crate::{
     bot::Msg,
-    data::{Config, Objective, Quest, Weapon},
+    data::{config_diff, load_config, Config, Objective, Quest, Weapon},
 };
 use indexmap::map::IndexMap;
 use itertools::Itertools;
-use once_cell::sync::Lazy;
+use once_cell::sync::{Lazy, OnceCell};
+use serenity::http::Http;
 use sqlite::Connection;
 use std::{
     io::Write,
+    path::{Path, PathBuf},
     sync::{Arc, Mutex},
+    time::Duration,
 };
 use strum::IntoEnumIterator;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
@@ -37,6 +40,9 @@ pub static DB_PATH: Lazy<std::path::PathBuf> = Lazy::new(|| {
     std::path::PathBuf::from(std::env::var("MHR_DB_PATH").expect("env var: MHR_DB_PATH"))
 });
 
+/// HTTP client of the running BOT, set when the BOT gets ready
+pub static HTTP: OnceCell<Arc<Http>> = OnceCell::new();
+
 /// SQLite Connection
 pub static CONN: Lazy<Arc<Mutex<Connection>>> = Lazy::new(|| {
     Arc::new(Mutex::new(
@@ -49,27 +55,221 @@ pub static CONFIG_PATH: Lazy<std::path::PathBuf> = Lazy::new(|| {
     std::path::PathBuf::from(std::env::var("MHR_CONFIG_PATH").expect("env var: MHR_CONFIG_PATH"))
 });
 
+/// MHR_CHART_FONT (optional): TrueType/OpenType font to draw charts with
+///
+/// It should cover Japanese for weapon and hunter names (e.g. Noto Sans CJK).
+/// Without a loadable font, statistics are shown as embed fields instead of charts.
+pub static CHART_FONT_PATH: Lazy<Option<std::path::PathBuf>> =
+    Lazy::new(|| std::env::var_os("MHR_CHART_FONT").map(std::path::PathBuf::from));
+
+/// Number of backups of the config file to keep
+const CONFIG_BACKUPS: usize = 5;
+
+/// Interval to check the config file for manual edits
+const WATCH_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Content of the config file last loaded or written by the BOT
+static SYNCED: Lazy<Mutex<String>> = Lazy::new(|| Mutex::new(String::new()));
+
 /// In-memory Configures
+///
+/// Falls back to the newest backup that can be parsed if the config file is broken,
+/// and moves the broken one aside (see [quarantine_config]).
 pub static CONFIG: Lazy<Arc<Mutex<Config>>> = Lazy::new(|| {
-    let config: Config = toml::from_str(&std::fs::read_to_string(&*CONFIG_PATH).unwrap()).unwrap();
-    Arc::new(Mutex::new(config))
+    let load = |path: &Path| -> anyhow::Result<Config> {
+        let content = std::fs::read_to_string(path)?;
+        let (config, report) = load_config(&content)?;
+        if !report.is_empty() {
+            let report = report.join("\n");
+            tracing::warn!("loaded {path:?} with fixes:\n{report}");
+            let _ = CENTRAL.sender().try_send(Msg::Event {
+                title: "config fixed on load".to_owned(),
+                description: Some(report),
+            });
+        }
+        *SYNCED.lock().unwrap() = content;
+        Ok(config)
+    };
+    let config = load(CONFIG_PATH.as_path()).or_else(|err| {
+        tracing::error!("failed to load {:?}: {err:?}", CONFIG_PATH.as_path());
+        config_backups()
+            .iter()
+            .find_map(|backup| match load(backup) {
+                Ok(config) => {
+                    tracing::warn!("fall back to the backup {backup:?}");
+                    if CONFIG_PATH.exists() {
+                        match quarantine_config() {
+                            Ok(path) => tracing::warn!("moved the broken config file to {path:?}"),
+                            Err(err) => {
+                                tracing::error!("failed to move the broken config file: {err:?}")
+                            }
+                        }
+                    }
+                    Some(config)
+                }
+                Err(err) => {
+                    tracing::error!("failed to load the backup {backup:?}: {err:?}");
+                    None
+                }
+            })
+            .ok_or(err)
+    });
+    Arc::new(Mutex::new(config.expect("no valid config file or backup")))
 });
 
+/// Directory of the config file and its backups
+fn config_dir() -> &'static Path {
+    CONFIG_PATH
+        .parent()
+        .filter(|dir| !dir.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."))
+}
+
+/// Moves the broken config file aside as `<config file name>.corrupt-<timestamp>`,
+/// so that the next [sync_all] does not keep it as the newest backup.
+fn quarantine_config() -> std::io::Result<PathBuf> {
+    let mut quarantine = CONFIG_PATH.clone().into_os_string();
+    quarantine.push(format!(
+        ".corrupt-{}",
+        chrono::Local::now().format("%Y%m%d%H%M%S%3f")
+    ));
+    std::fs::rename(CONFIG_PATH.as_path(), &quarantine)?;
+    Ok(PathBuf::from(quarantine))
+}
+
+/// Returns backups of the config file, newest first.
+///
+/// Backups are named `<config file name>.<timestamp>.bak` and placed next to the config file.
+fn config_backups() -> Vec<PathBuf> {
+    let dir = config_dir();
+    let prefix = format!(
+        "{}.",
+        CONFIG_PATH
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+    );
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    let name = path.file_name().unwrap_or_default().to_string_lossy();
+                    name.starts_with(&prefix) && name.ends_with(".bak")
+                })
+                // timestamps are zero-padded, so names sort chronologically
+                .sorted()
+                .rev()
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Write all configures to toml file
+///
+/// The new content is written to a temporary file and then renamed over the config file,
+/// so that a crash in the middle never leaves a broken config file.
+/// The previous config file is kept as a backup, up to [CONFIG_BACKUPS] files.
+/// Fails without writing if the config file differs from [SYNCED],
+/// i.e. it has been edited by hand and [watch_config] has not reloaded it yet.
 pub fn sync_all() -> std::result::Result<(), std::io::Error> {
-    let mut conf = std::fs::OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(CONFIG_PATH.as_path())?;
-    conf.write_all(
-        toml::to_string_pretty(&*CONFIG.lock().unwrap())
-            .unwrap()
-            .as_bytes(),
-    )?;
+    let content = toml::to_string_pretty(&*CONFIG.lock().unwrap())
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))?;
+
+    // Hold SYNCED until the rename, so that the watcher never takes our own write for a manual edit.
+    let mut synced = SYNCED.lock().unwrap();
+    match std::fs::read_to_string(CONFIG_PATH.as_path()) {
+        Ok(on_disk) if on_disk != *synced => {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::Other,
+                "the config file has been edited by hand; try again after it is reloaded",
+            ));
+        }
+        Err(err) if err.kind() != std::io::ErrorKind::NotFound => return Err(err),
+        _ => {}
+    }
+    let mut temporary = CONFIG_PATH.clone().into_os_string();
+    temporary.push(".tmp");
+    let temporary = PathBuf::from(temporary);
+    let mut conf = std::fs::File::create(&temporary)?;
+    conf.write_all(content.as_bytes())?;
     conf.sync_all()?;
+
+    if CONFIG_PATH.exists() {
+        let mut backup = CONFIG_PATH.clone().into_os_string();
+        backup.push(format!(
+            ".{}.bak",
+            chrono::Local::now().format("%Y%m%d%H%M%S%3f")
+        ));
+        std::fs::copy(CONFIG_PATH.as_path(), backup)?;
+    }
+    std::fs::rename(&temporary, CONFIG_PATH.as_path())?;
+    // Persist the rename itself, which lives in the directory entry.
+    std::fs::File::open(config_dir())?.sync_all()?;
+    *synced = content;
+    drop(synced);
+
+    for outdated in config_backups().iter().skip(CONFIG_BACKUPS) {
+        std::fs::remove_file(outdated)?;
+    }
     Ok(())
 }
 
+/// Watches the config file and reloads [CONFIG] when it is edited by hand.
+///
+/// Valid edits replace the in-memory configures and are notified to [CENTRAL] as an event.
+/// Invalid edits are rejected (the in-memory configures are kept), and logged with the diff.
+pub fn watch_config() -> std::thread::JoinHandle<()> {
+    Lazy::force(&CONFIG);
+    std::thread::spawn(|| {
+        let modified = || {
+            std::fs::metadata(CONFIG_PATH.as_path())
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        };
+        let mut last_modified = modified();
+        loop {
+            std::thread::sleep(WATCH_INTERVAL);
+            let current = modified();
+            if current == last_modified {
+                continue;
+            }
+            last_modified = current;
+
+            let mut synced = SYNCED.lock().unwrap();
+            let content = match std::fs::read_to_string(CONFIG_PATH.as_path()) {
+                Ok(content) if content != *synced => content,
+                _ => continue,
+            };
+            let diff = config_diff(&synced, &content);
+            let msg = match load_config(&content) {
+                Ok((config, report)) => {
+                    *CONFIG.lock().unwrap() = config;
+                    *synced = content;
+                    let report = report.join("\n");
+                    tracing::info!("reloaded {:?}:\n{diff}\n{report}", CONFIG_PATH.as_path());
+                    Msg::Event {
+                        title: "config reloaded".to_owned(),
+                        description: Some(format!("{diff}\n{report}")),
+                    }
+                }
+                Err(err) => {
+                    tracing::error!(
+                        "rejected invalid edit of {:?}: {err:#}\n{diff}",
+                        CONFIG_PATH.as_path()
+                    );
+                    Msg::Event {
+                        title: "config edit rejected".to_owned(),
+                        description: Some(format!("{err:#}\n{diff}")),
+                    }
+                }
+            };
+            drop(synced);
+            let _ = CENTRAL.sender().blocking_send(msg);
+        }
+    })
+}
+
 /// Struct that holds sender and receiver
 pub struct Tsx<T> {
     sender: Arc<Sender<T>>,
@@ -53,6 +53,9 @@ async fn main() -> anyhow::Result<()> {
             .personal_token(std::env::var("GITHUB_PERSONAL_ACCESS_TOKEN")?))
     })?;
 
+    // reload the config file on manual edits
+    global::watch_config();
+
     // spawn bot client
     tokio::spawn(async move {
         let mut client = prepare_bot_client().await.expect("client");
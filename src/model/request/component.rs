@@ -28,6 +28,7 @@ pub enum Component {
         min_value: u64,
         max_value: u64,
         options: Vec<SelectMenuOption>,
+        disabled: bool,
     },
 }
 
@@ -25,12 +25,79 @@ pub enum Message {
     Embed(serenity::builder::CreateEmbed),
 }
 
+/// Who can see a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Visibility {
+    /// Everyone in the channel
+    Public,
+    /// Only the user who invoked the interaction
+    Ephemeral,
+}
+
+/// File attached to a message.
+pub struct Attachment {
+    pub filename: String,
+    pub data: Vec<u8>,
+}
+
+impl std::fmt::Debug for Attachment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Attachment")
+            .field("filename", &self.filename)
+            .field("size", &self.data.len())
+            .finish()
+    }
+}
+
 #[derive(Debug)]
 pub enum Request {
     Message(Message),
     Components(Component),
+    /// Message with action rows (one row per component)
+    Interactive {
+        message: Message,
+        components: Vec<Component>,
+    },
+    /// Message with files (an embed can show one with `attachment://<filename>`)
+    ///
+    /// Responses cannot be edited to attach files, so it is sent as a follow-up.
+    Attachments {
+        message: Message,
+        files: Vec<Attachment>,
+    },
+    /// Updates the message that the component is attached to
     Update {
         content: String,
-        component: Option<Component>,
+        embeds: Vec<serenity::builder::CreateEmbed>,
+        components: Vec<Component>,
     },
+    /// Replaces the components of the message that the component is attached to,
+    /// leaving its content and embeds as they are
+    UpdateComponents(Vec<Component>),
+    /// Request with a visibility flag (requests without it are public)
+    WithVisibility(Visibility, Box<Request>),
+}
+
+impl Request {
+    /// Marks the request as visible only to the invoker.
+    pub fn ephemeral(self) -> Request {
+        self.with_visibility(Visibility::Ephemeral)
+    }
+
+    pub fn with_visibility(self, visibility: Visibility) -> Request {
+        match self {
+            Request::WithVisibility(_, request) => Request::WithVisibility(visibility, request),
+            request => Request::WithVisibility(visibility, Box::new(request)),
+        }
+    }
+
+    /// Splits the request into its visibility and the request itself.
+    pub fn split_visibility(self) -> (Visibility, Request) {
+        match self {
+            Request::WithVisibility(visibility, request) => {
+                (visibility, request.split_visibility().1)
+            }
+            request => (Visibility::Public, request),
+        }
+    }
 }
@@ -17,7 +17,7 @@
  *
  */
 
-use serenity::model::user::User;
+use serenity::model::{guild::Role, user::User};
 use strum_macros::{AsRefStr, EnumIter, EnumString, IntoStaticStr};
 
 #[derive(Debug, Clone, Copy, AsRefStr, IntoStaticStr)]
@@ -27,6 +27,9 @@ pub(crate) enum Commands {
     Settings,
     Generate,
     Statistics,
+    Quests,
+    Roulette,
+    Profile,
 }
 
 #[derive(
@@ -39,6 +42,103 @@ pub(crate) enum Options {
     Remove,
 }
 
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum GuestOptions {
+    Add,
+    Remove,
+    Merge,
+}
+
+/// Anti-repeat window of `/settings recency`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum RecencyScope {
+    /// Last weapons of each member
+    Weapons,
+    /// Last quests or monsters of the guild
+    Targets,
+}
+
+/// Role of `/settings roles`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum RoleKind {
+    /// Role with the same authority as server administrators
+    Admin,
+    /// "roulette manager" role
+    Manager,
+}
+
+/// How `/statistics query` shows the counts.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum ChartKind {
+    /// Horizontal bar chart image
+    Bar,
+    /// Radar chart image
+    Radar,
+    /// Embed fields
+    Fields,
+}
+
+/// What counts as covered on a coverage card.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum CoverageBasis {
+    /// Assigned in any roll
+    Assigned,
+    /// Assigned in a cleared roll
+    Cleared,
+}
+
+/// File format of `/statistics export`.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum ExportFormat {
+    Csv,
+    Json,
+}
+
+/// Period to count rolls per in `/statistics activity`, in the time zone of the guild.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum Bucket {
+    Day,
+    Week,
+    Month,
+}
+
+/// Score to rank hunters by.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
+)]
+#[strum(serialize_all = "snake_case")]
+pub(crate) enum RankingBy {
+    /// Number of rolls
+    Rolls,
+    /// Number of rolls with a weapon
+    Weapon,
+    /// Number of cleared rolls
+    Clears,
+    /// Number of distinct weapons
+    Variety,
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, AsRefStr, IntoStaticStr, EnumString, EnumIter,
 )]
@@ -62,12 +162,47 @@ pub(crate) enum About {
 
 #[derive(Debug)]
 pub(crate) enum SettingsSubCommands {
+    Help,
     Info(About),
-    Members(Options, Vec<User>),
+    Members {
+        option: Options,
+        users: Vec<User>,
+        /// Space-separated user mentions or IDs
+        mentions: String,
+        roles: Vec<Role>,
+    },
     Range,
     Exclude(Options, Choices, String),
     Target(Options, Choices, String),
     Obliterate(Choices),
+    History,
+    Undo,
+    Guest {
+        option: GuestOptions,
+        name: String,
+        /// User to merge the guest into
+        user: Option<User>,
+    },
+    /// Shows the time zone of the guild, or sets it if given
+    TimeZone(Option<String>),
+    /// Shows the anti-repeat windows, or sets one of them if given
+    Recency(Option<(RecencyScope, i64)>),
+    /// Sets the weight of the items, written as e.g. `0.2` or `x2`
+    Weight(Choices, String, String),
+    /// Shows the admin and manager roles, or sets one of them (unsets it without a role)
+    Roles(Option<(RoleKind, Option<Role>)>),
+}
+
+#[derive(Debug)]
+pub(crate) enum ProfileSubCommands {
+    Set { hr: i64, star: i64 },
+    Show(Option<User>),
+}
+
+#[derive(Debug)]
+pub(crate) enum RouletteSubCommands {
+    Join,
+    Leave,
 }
 
 #[derive(Debug)]
@@ -78,5 +213,54 @@ pub(crate) enum StatisticsSubCommands {
         weapon: Option<String>,
         since: Option<String>,
         until: Option<String>,
+        /// [ChartKind::Bar] if absent
+        chart: Option<ChartKind>,
+    },
+    Compare {
+        a: User,
+        b: User,
+        weapon: Option<String>,
+        since: Option<String>,
+        until: Option<String>,
+    },
+    /// Most rolled monsters, of a hunter if given
+    Monsters {
+        user: Option<User>,
+        since: Option<String>,
+        until: Option<String>,
+    },
+    /// Most rolled and never rolled quests
+    Quests {
+        since: Option<String>,
+        until: Option<String>,
+    },
+    /// Coverage card of a hunter (the invoker if absent)
+    Coverage {
+        user: Option<User>,
+        /// [CoverageBasis::Cleared] if absent
+        basis: Option<CoverageBasis>,
+        restrictions: bool,
+        monsters: bool,
+    },
+    Export {
+        /// All hunters if absent
+        user: Option<User>,
+        format: ExportFormat,
+        weapon: Option<String>,
+        since: Option<String>,
+        until: Option<String>,
+    },
+    Ranking {
+        by: RankingBy,
+        weapon: Option<String>,
+        since: Option<String>,
+        until: Option<String>,
+    },
+    /// Rolls per day, week or month, of a hunter if given
+    Activity {
+        user: Option<User>,
+        per: Bucket,
+        since: Option<String>,
+        until: Option<String>,
     },
 }
@@ -19,4 +19,17 @@
 
 pub enum ComponentMsg {
     Range(Vec<usize>),
+    /// Page of the quest catalog to show
+    Quests(usize),
+    /// Token of the confirmation to apply
+    Confirm(u64),
+    /// Token of the confirmation to discard
+    Cancel(u64),
+    /// Page of a ranking, and the ranking encoded by the ranking executor
+    Ranking {
+        page: usize,
+        state: String,
+    },
+    /// Roll to mark as cleared
+    Cleared(i64),
 }
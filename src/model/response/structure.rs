@@ -37,3 +37,31 @@ pub enum Response {
     SlashCommand(SlashCommand),
     Component(Component),
 }
+
+/// User who invoked the interaction.
+#[derive(Debug, Clone)]
+pub struct Invoker {
+    pub user: serenity::model::user::User,
+    /// Roles of the user (empty outside of guilds)
+    pub roles: Vec<serenity::model::id::RoleId>,
+    /// Permissions of the user in the channel (`None` outside of guilds)
+    pub permissions: Option<serenity::model::permissions::Permissions>,
+    pub guild_id: Option<serenity::model::id::GuildId>,
+}
+
+impl Invoker {
+    pub fn new(
+        user: &serenity::model::user::User,
+        member: Option<&serenity::model::guild::Member>,
+        guild_id: Option<serenity::model::id::GuildId>,
+    ) -> Invoker {
+        Invoker {
+            user: user.clone(),
+            roles: member
+                .map(|member| member.roles.clone())
+                .unwrap_or_default(),
+            permissions: member.and_then(|member| member.permissions),
+            guild_id,
+        }
+    }
+}
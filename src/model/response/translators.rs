@@ -20,7 +20,7 @@
 use super::{commands::*, Response, SlashCommand};
 use crate::{
     concepts::SameAs,
-    error::LogicError,
+    error::{CommandError, LogicError},
     model::{
         response::{Component, ComponentMsg},
         translate::TranslateTo,
@@ -80,6 +80,20 @@ impl TranslateTo<i64> for Response {
     }
 }
 
+impl TranslateTo<bool> for Response {
+    fn translate_to<T>(&self) -> anyhow::Result<bool>
+    where
+        T: SameAs<bool>,
+    {
+        if let Response::SlashCommand(SlashCommand::Option(boxed)) = self {
+            if let OptionValue::Boolean(value) = &**boxed {
+                return Ok(*value);
+            }
+        }
+        Err(anyhow::anyhow!("cannot translate to Boolean: {:?}", &self))
+    }
+}
+
 impl TranslateTo<User> for Response {
     fn translate_to<T>(&self) -> anyhow::Result<User>
     where
@@ -140,6 +154,15 @@ impl TranslateTo<Commands> for Response {
             Response::SlashCommand(SlashCommand::Command(cmd)) if cmd == "statistics" => {
                 Ok(Commands::Statistics)
             }
+            Response::SlashCommand(SlashCommand::Command(cmd)) if cmd == "quests" => {
+                Ok(Commands::Quests)
+            }
+            Response::SlashCommand(SlashCommand::Command(cmd)) if cmd == "roulette" => {
+                Ok(Commands::Roulette)
+            }
+            Response::SlashCommand(SlashCommand::Command(cmd)) if cmd == "profile" => {
+                Ok(Commands::Profile)
+            }
             unknown => Err(anyhow::anyhow!(
                 "ERROR: cannot translate to Commands {:?}",
                 unknown
@@ -167,6 +190,25 @@ impl TranslateTo<Options> for Response {
     }
 }
 
+impl TranslateTo<GuestOptions> for Response {
+    fn translate_to<T>(&self) -> anyhow::Result<GuestOptions>
+    where
+        T: SameAs<GuestOptions>,
+    {
+        if let Response::SlashCommand(SlashCommand::Option(boxed)) = self {
+            if let OptionValue::String(opt) = &**boxed {
+                return match &opt[..] {
+                    "add" => Ok(GuestOptions::Add),
+                    "remove" => Ok(GuestOptions::Remove),
+                    "merge" => Ok(GuestOptions::Merge),
+                    _ => anyhow::bail!("ERROR: cannot translate: {}", opt),
+                };
+            }
+        }
+        Err(anyhow::anyhow!("ERROR: cannot translate: {:?}", &self))
+    }
+}
+
 impl TranslateTo<Choices> for Response {
     fn translate_to<T>(&self) -> anyhow::Result<Choices>
     where
@@ -212,18 +254,23 @@ impl TranslateTo<SettingsSubCommands> for &[Response] {
         T: SameAs<SettingsSubCommands>,
     {
         match self {
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "help" => {
+                Ok(SettingsSubCommands::Help)
+            }
             [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), choice]
                 if sub_cmd == "info" =>
             {
                 Ok(SettingsSubCommands::Info(choice.translate_to::<About>()?))
             }
-            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), option, users @ ..]
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), option, rest @ ..]
                 if sub_cmd == "members" =>
             {
-                Ok(SettingsSubCommands::Members(
-                    option.translate_to::<Options>()?,
-                    users.translate_to::<Vec<User>>()?,
-                ))
+                Ok(SettingsSubCommands::Members {
+                    option: option.translate_to::<Options>()?,
+                    users: rest.translate_to::<Vec<User>>()?,
+                    mentions: rest.translate_to::<Vec<String>>()?.join(" "),
+                    roles: rest.translate_to::<Vec<Role>>()?,
+                })
             }
             [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "range" => {
                 Ok(SettingsSubCommands::Range)
@@ -253,6 +300,81 @@ impl TranslateTo<SettingsSubCommands> for &[Response] {
                     choice.translate_to::<Choices>()?,
                 ))
             }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "history" => {
+                Ok(SettingsSubCommands::History)
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "undo" => {
+                Ok(SettingsSubCommands::Undo)
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), option, name, user @ ..]
+                if sub_cmd == "guest" =>
+            {
+                Ok(SettingsSubCommands::Guest {
+                    option: option.translate_to::<GuestOptions>()?,
+                    name: name.translate_to::<String>()?,
+                    user: user.translate_to::<Vec<User>>()?.into_iter().next(),
+                })
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))]
+                if sub_cmd == "timezone" =>
+            {
+                Ok(SettingsSubCommands::TimeZone(None))
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), zone]
+                if sub_cmd == "timezone" =>
+            {
+                Ok(SettingsSubCommands::TimeZone(Some(
+                    zone.translate_to::<String>()?,
+                )))
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), choice, arg, weight]
+                if sub_cmd == "weight" =>
+            {
+                Ok(SettingsSubCommands::Weight(
+                    choice.translate_to::<Choices>()?,
+                    arg.translate_to::<String>()?,
+                    weight.translate_to::<String>()?,
+                ))
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "recency" => {
+                Ok(SettingsSubCommands::Recency(None))
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), scope, count]
+                if sub_cmd == "recency" =>
+            {
+                Ok(SettingsSubCommands::Recency(Some((
+                    scope
+                        .translate_to::<String>()?
+                        .parse::<RecencyScope>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    count.translate_to::<i64>()?,
+                ))))
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), _]
+                if sub_cmd == "recency" =>
+            {
+                bailout!(
+                    "Partial recency options",
+                    CommandError::InvalidArgument {
+                        arg: "give both scope and count, or neither of them".to_string(),
+                    }
+                );
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), rest @ ..]
+                if sub_cmd == "roles" && rest.len() <= 2 =>
+            {
+                Ok(SettingsSubCommands::Roles(match rest {
+                    [] => None,
+                    [kind, role @ ..] => Some((
+                        kind.translate_to::<String>()?
+                            .parse::<RoleKind>()
+                            .with_context(|| anyhow::anyhow!("parse failed"))?,
+                        role.first()
+                            .map(|role| role.translate_to::<Role>())
+                            .transpose()?,
+                    )),
+                }))
+            }
             // start without sub-command
             unknown => {
                 let expr = stringify!(self);
@@ -270,6 +392,71 @@ impl TranslateTo<SettingsSubCommands> for &[Response] {
     }
 }
 
+impl TranslateTo<ProfileSubCommands> for &[Response] {
+    fn translate_to<T>(&self) -> anyhow::Result<ProfileSubCommands>
+    where
+        T: SameAs<ProfileSubCommands>,
+    {
+        match self {
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), hr, star]
+                if sub_cmd == "set" =>
+            {
+                Ok(ProfileSubCommands::Set {
+                    hr: hr.translate_to::<i64>()?,
+                    star: star.translate_to::<i64>()?,
+                })
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd)), user @ ..]
+                if sub_cmd == "show" =>
+            {
+                Ok(ProfileSubCommands::Show(
+                    user.translate_to::<Vec<User>>()?.into_iter().next(),
+                ))
+            }
+            unknown => {
+                let expr = stringify!(self);
+                let typename = std::any::type_name_of_val(unknown);
+                bailout!(
+                    "Unknown sub-command",
+                    LogicError::UnreachableGuard {
+                        expr: format!("{expr}: {typename}"),
+                        value: format!("{unknown:?}"),
+                        info: pretty_info!(),
+                    }
+                );
+            }
+        }
+    }
+}
+
+impl TranslateTo<RouletteSubCommands> for &[Response] {
+    fn translate_to<T>(&self) -> anyhow::Result<RouletteSubCommands>
+    where
+        T: SameAs<RouletteSubCommands>,
+    {
+        match self {
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "join" => {
+                Ok(RouletteSubCommands::Join)
+            }
+            [Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))] if sub_cmd == "leave" => {
+                Ok(RouletteSubCommands::Leave)
+            }
+            unknown => {
+                let expr = stringify!(self);
+                let typename = std::any::type_name_of_val(unknown);
+                bailout!(
+                    "Unknown sub-command",
+                    LogicError::UnreachableGuard {
+                        expr: format!("{expr}: {typename}"),
+                        value: format!("{unknown:?}"),
+                        info: pretty_info!(),
+                    }
+                );
+            }
+        }
+    }
+}
+
 impl TranslateTo<StatisticsSubCommands> for &[(String, Response)] {
     fn translate_to<T>(&self) -> anyhow::Result<StatisticsSubCommands>
     where
@@ -304,6 +491,172 @@ impl TranslateTo<StatisticsSubCommands> for &[(String, Response)] {
                     weapon: queries.get("weapon").cloned(),
                     since: queries.get("since").cloned(),
                     until: queries.get("until").cloned(),
+                    chart: queries
+                        .get("chart")
+                        .map(|chart| chart.parse::<ChartKind>())
+                        .transpose()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "compare" =>
+            {
+                let user = |name: &str| {
+                    queryable
+                        .iter()
+                        .filter(|(key, _)| key == name)
+                        .filter_map(|(_, item)| item.translate_to::<User>().ok())
+                        .next()
+                        .with_context(|| anyhow::anyhow!("no user found for {}.", name))
+                };
+
+                let queries = queryable
+                    .iter()
+                    .filter_map(|(key, item)| {
+                        item.translate_to::<String>()
+                            .ok()
+                            .map(|query| (key.clone(), query))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                Ok(StatisticsSubCommands::Compare {
+                    a: user("a")?,
+                    b: user("b")?,
+                    weapon: queries.get("weapon").cloned(),
+                    since: queries.get("since").cloned(),
+                    until: queries.get("until").cloned(),
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "monsters" || sub_cmd == "quests" =>
+            {
+                let user = queryable
+                    .iter()
+                    .filter_map(|(_, item)| item.translate_to::<User>().ok())
+                    .next();
+
+                let queries = queryable
+                    .iter()
+                    .filter_map(|(key, item)| {
+                        item.translate_to::<String>()
+                            .ok()
+                            .map(|query| (key.clone(), query))
+                    })
+                    .collect::<HashMap<_, _>>();
+                let since = queries.get("since").cloned();
+                let until = queries.get("until").cloned();
+
+                Ok(if sub_cmd == "monsters" {
+                    StatisticsSubCommands::Monsters { user, since, until }
+                } else {
+                    StatisticsSubCommands::Quests { since, until }
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "coverage" =>
+            {
+                let flag = |name: &str| {
+                    queryable
+                        .iter()
+                        .filter(|(key, _)| key == name)
+                        .any(|(_, item)| item.translate_to::<bool>().unwrap_or_default())
+                };
+
+                Ok(StatisticsSubCommands::Coverage {
+                    user: queryable
+                        .iter()
+                        .filter_map(|(_, item)| item.translate_to::<User>().ok())
+                        .next(),
+                    basis: queryable
+                        .iter()
+                        .filter(|(key, _)| key == "basis")
+                        .filter_map(|(_, item)| item.translate_to::<String>().ok())
+                        .next()
+                        .map(|basis| basis.parse::<CoverageBasis>())
+                        .transpose()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    restrictions: flag("restrictions"),
+                    monsters: flag("monsters"),
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "export" =>
+            {
+                let user = queryable
+                    .iter()
+                    .filter_map(|(_, item)| item.translate_to::<User>().ok())
+                    .next();
+
+                let queries = queryable
+                    .iter()
+                    .filter_map(|(key, item)| {
+                        item.translate_to::<String>()
+                            .ok()
+                            .map(|query| (key.clone(), query))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                Ok(StatisticsSubCommands::Export {
+                    user,
+                    format: queries
+                        .get("format")
+                        .with_context(|| anyhow::anyhow!("no format found."))?
+                        .parse::<ExportFormat>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    weapon: queries.get("weapon").cloned(),
+                    since: queries.get("since").cloned(),
+                    until: queries.get("until").cloned(),
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "ranking" =>
+            {
+                let queries = queryable
+                    .iter()
+                    .filter_map(|(key, item)| {
+                        item.translate_to::<String>()
+                            .ok()
+                            .map(|query| (key.clone(), query))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                Ok(StatisticsSubCommands::Ranking {
+                    by: queries
+                        .get("by")
+                        .with_context(|| anyhow::anyhow!("no ranking key found."))?
+                        .parse::<RankingBy>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    weapon: queries.get("weapon").cloned(),
+                    since: queries.get("since").cloned(),
+                    until: queries.get("until").cloned(),
+                })
+            }
+            [(_, Response::SlashCommand(SlashCommand::SubCommand(sub_cmd))), queryable @ ..]
+                if sub_cmd == "activity" =>
+            {
+                let user = queryable
+                    .iter()
+                    .filter_map(|(_, item)| item.translate_to::<User>().ok())
+                    .next();
+
+                let queries = queryable
+                    .iter()
+                    .filter_map(|(key, item)| {
+                        item.translate_to::<String>()
+                            .ok()
+                            .map(|query| (key.clone(), query))
+                    })
+                    .collect::<HashMap<_, _>>();
+
+                Ok(StatisticsSubCommands::Activity {
+                    user,
+                    per: queries
+                        .get("per")
+                        .with_context(|| anyhow::anyhow!("no bucket found."))?
+                        .parse::<Bucket>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    since: queries.get("since").cloned(),
+                    until: queries.get("until").cloned(),
                 })
             }
             // start without sub-command
@@ -323,20 +676,88 @@ impl TranslateTo<StatisticsSubCommands> for &[(String, Response)] {
     }
 }
 
-impl TranslateTo<ComponentMsg> for Response {
+/// Translates a component interaction, keyed by its `custom_id`.
+impl TranslateTo<ComponentMsg> for (String, Response) {
     fn translate_to<T>(&self) -> anyhow::Result<ComponentMsg>
     where
         T: SameAs<ComponentMsg>,
     {
         match self {
-            Response::Component(Component::SelectMenu(msg)) => Ok(ComponentMsg::Range(
-                msg.iter()
-                    .map(|rank| {
-                        rank.parse::<usize>()
-                            .with_context(|| anyhow::anyhow!("parse failed"))
-                    })
-                    .collect::<anyhow::Result<Vec<_>>>()?,
-            )),
+            (custom_id, Response::Component(Component::SelectMenu(msg)))
+                if custom_id == "range" =>
+            {
+                Ok(ComponentMsg::Range(
+                    msg.iter()
+                        .map(|rank| {
+                            rank.parse::<usize>()
+                                .with_context(|| anyhow::anyhow!("parse failed"))
+                        })
+                        .collect::<anyhow::Result<Vec<_>>>()?,
+                ))
+            }
+            (custom_id, Response::Component(Component::SelectMenu(msg)))
+                if custom_id == "quests" =>
+            {
+                Ok(ComponentMsg::Quests(
+                    msg.first()
+                        .with_context(|| anyhow::anyhow!("no rank selected"))?
+                        .parse::<usize>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                ))
+            }
+            // quests:prev:<rank> or quests:next:<rank>
+            (custom_id, Response::Component(Component::Button(_)))
+                if custom_id.starts_with("quests:") =>
+            {
+                Ok(ComponentMsg::Quests(
+                    custom_id
+                        .rsplit(':')
+                        .next()
+                        .unwrap_or_default()
+                        .parse::<usize>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                ))
+            }
+            // confirm:<token> or cancel:<token>
+            (custom_id, Response::Component(Component::Button(_)))
+                if custom_id.starts_with("confirm:") || custom_id.starts_with("cancel:") =>
+            {
+                let (action, token) = custom_id
+                    .split_once(':')
+                    .with_context(|| anyhow::anyhow!("no token"))?;
+                let token = token
+                    .parse::<u64>()
+                    .with_context(|| anyhow::anyhow!("parse failed"))?;
+                Ok(if action == "confirm" {
+                    ComponentMsg::Confirm(token)
+                } else {
+                    ComponentMsg::Cancel(token)
+                })
+            }
+            // ranking:<page>:<state>
+            (custom_id, Response::Component(Component::Button(_)))
+                if custom_id.starts_with("ranking:") =>
+            {
+                let (page, state) = custom_id["ranking:".len()..]
+                    .split_once(':')
+                    .with_context(|| anyhow::anyhow!("no state"))?;
+                Ok(ComponentMsg::Ranking {
+                    page: page
+                        .parse::<usize>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                    state: state.to_string(),
+                })
+            }
+            // cleared:<roll>
+            (custom_id, Response::Component(Component::Button(_)))
+                if custom_id.starts_with("cleared:") =>
+            {
+                Ok(ComponentMsg::Cleared(
+                    custom_id["cleared:".len()..]
+                        .parse::<i64>()
+                        .with_context(|| anyhow::anyhow!("parse failed"))?,
+                ))
+            }
             unknown => Err(anyhow::anyhow!(
                 "ERROR: cannot translate to Commands {:?}",
                 unknown
@@ -18,7 +18,9 @@
  */
 
 mod parse;
+mod selector;
 mod validators;
 
 pub use parse::*;
+pub use selector::*;
 pub use validators::*;
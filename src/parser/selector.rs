@@ -0,0 +1,197 @@
+/*
+ * ISC License
+ *
+ * Copyright (c) 2021 Mitama Lab
+ *
+ * Permission to use, copy, modify, and/or distribute this software for any
+ * purpose with or without fee is hereby granted, provided that the above
+ * copyright notice and this permission notice appear in all copies.
+ *
+ * THE SOFTWARE IS PROVIDED "AS IS" AND THE AUTHOR DISCLAIMS ALL WARRANTIES
+ * WITH REGARD TO THIS SOFTWARE INCLUDING ALL IMPLIED WARRANTIES OF
+ * MERCHANTABILITY AND FITNESS. IN NO EVENT SHALL THE AUTHOR BE LIABLE FOR
+ * ANY SPECIAL, DIRECT, INDIRECT, OR CONSEQUENTIAL DAMAGES OR ANY DAMAGES
+ * WHATSOEVER RESULTING FROM LOSS OF USE, DATA OR PROFITS, WHETHER IN AN
+ * ACTION OF CONTRACT, NEGLIGENCE OR OTHER TORTIOUS ACTION, ARISING OUT OF
+ * OR IN CONNECTION WITH THE USE OR PERFORMANCE OF THIS SOFTWARE.
+ *
+ */
+
+use std::str::FromStr;
+
+use boolinator::Boolinator;
+use itertools::Itertools;
+use lazy_regex::regex_captures;
+use strum::IntoEnumIterator;
+
+use crate::{
+    data::{Monster, QuestID},
+    error::CommandError,
+};
+
+/// # Quest Selector
+///
+/// Selects one or more quests in the catalog.
+///
+/// - `<rank>-<no>` (e.g. `5-12`): a single quest
+/// - `<id>..<id>` (e.g. `5-1..5-8`): quests between two IDs (inclusive)
+/// - `<rank>-*` (e.g. `6-*`): all quests of a rank
+/// - `with:<monster>` (e.g. `with:rathian`, `with:リオレイア`): all quests with the monster
+/// - `<title>` (e.g. `女王に魅せられて`): quests with the title
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuestSelector {
+    Single(QuestID),
+    Range(QuestID, QuestID),
+    Rank(u32),
+    Monster(Monster),
+    Title(String),
+}
+
+impl FromStr for QuestSelector {
+    type Err = CommandError;
+
+    fn from_str(selector: &str) -> Result<Self, Self::Err> {
+        let invalid = || CommandError::InvalidArgument {
+            arg: selector.to_string(),
+        };
+        let number = |digits: &str| digits.parse::<u32>().map_err(|_| invalid());
+
+        if let Some((_, rank, no)) = regex_captures!(r"^([0-9])-([0-9]+)$", selector) {
+            Ok(QuestSelector::Single(QuestID(number(rank)?, number(no)?)))
+        } else if let Some((_, rank_a, no_a, rank_b, no_b)) =
+            regex_captures!(r"^([0-9])-([0-9]+)\.\.([0-9])-([0-9]+)$", selector)
+        {
+            Ok(QuestSelector::Range(
+                QuestID(number(rank_a)?, number(no_a)?),
+                QuestID(number(rank_b)?, number(no_b)?),
+            ))
+        } else if let Some((_, rank)) = regex_captures!(r"^([0-9])-\*$", selector) {
+            Ok(QuestSelector::Rank(number(rank)?))
+        } else if let Some(name) = selector.strip_prefix("with:") {
+            Monster::from_str(name)
+                .ok()
+                .or_else(|| Monster::iter().find(|monster| monster.ja() == name))
+                .map(QuestSelector::Monster)
+                .ok_or_else(invalid)
+        } else if selector.is_empty() {
+            Err(invalid())
+        } else {
+            Ok(QuestSelector::Title(selector.to_string()))
+        }
+    }
+}
+
+impl QuestSelector {
+    /// Expands the selector into quest IDs, validated against the catalog.
+    pub fn expand(&self) -> Result<Vec<QuestID>, CommandError> {
+        let expanded = match self {
+            QuestSelector::Single(id) => id.quest().map(|_| vec![*id]).unwrap_or_default(),
+            QuestSelector::Range(first, last)
+                if first.quest().is_some() && last.quest().is_some() =>
+            {
+                QuestID::all()
+                    .skip_while(|id| id < first)
+                    .take_while(|id| id <= last)
+                    .collect_vec()
+            }
+            QuestSelector::Range(..) => vec![],
+            QuestSelector::Rank(rank) => QuestID::all().filter(|id| id.0 == *rank).collect_vec(),
+            QuestSelector::Monster(monster) => QuestID::all()
+                .filter(|id| {
+                    id.quest()
+                        .map_or(false, |quest| quest.monsters().contains(monster))
+                })
+                .collect_vec(),
+            QuestSelector::Title(title) => QuestID::all()
+                .filter(|id| {
+                    id.quest()
+                        .map_or(false, |quest| quest.title() == title.as_str())
+                })
+                .collect_vec(),
+        };
+        (!expanded.is_empty())
+            .as_some(expanded)
+            .ok_or_else(|| CommandError::NoSuchQuest {
+                selector: self.to_string(),
+            })
+    }
+}
+
+impl std::fmt::Display for QuestSelector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            QuestSelector::Single(id) => write!(f, "{id}"),
+            QuestSelector::Range(first, last) => write!(f, "{first}..{last}"),
+            QuestSelector::Rank(rank) => write!(f, "{rank}-*"),
+            QuestSelector::Monster(monster) => write!(f, "with:{}", monster.ja()),
+            QuestSelector::Title(title) => write!(f, "{title}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_test() {
+        assert_eq!(
+            "5-12".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Single(QuestID(5, 12))
+        );
+        assert_eq!(
+            "0-3".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Single(QuestID(0, 3))
+        );
+        assert_eq!(
+            "5-1..5-8".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Range(QuestID(5, 1), QuestID(5, 8))
+        );
+        assert_eq!(
+            "6-*".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Rank(6)
+        );
+        assert_eq!(
+            "with:rathian".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Monster(Monster::Rathian)
+        );
+        assert_eq!(
+            "with:リオレイア".parse::<QuestSelector>().unwrap(),
+            QuestSelector::Monster(Monster::Rathian)
+        );
+        assert!("with:unknown".parse::<QuestSelector>().is_err());
+    }
+
+    #[test]
+    fn expand_test() {
+        assert_eq!(
+            QuestSelector::Single(QuestID(5, 12)).expand().unwrap(),
+            vec![QuestID(5, 12)]
+        );
+        assert_eq!(
+            QuestSelector::Range(QuestID(5, 1), QuestID(5, 3))
+                .expand()
+                .unwrap(),
+            vec![QuestID(5, 1), QuestID(5, 2), QuestID(5, 3)]
+        );
+        assert_eq!(QuestSelector::Rank(6).expand().unwrap().len(), 13);
+        assert!(QuestSelector::Rank(1).expand().is_err());
+        assert!(QuestSelector::Single(QuestID(5, 99)).expand().is_err());
+        assert!(QuestSelector::Range(QuestID(5, 3), QuestID(5, 1))
+            .expand()
+            .is_err());
+        assert!(QuestSelector::Monster(Monster::Rathian)
+            .expand()
+            .unwrap()
+            .contains(&QuestID(5, 1)));
+        assert_eq!(
+            QuestSelector::Title("女王に魅せられて".to_string())
+                .expand()
+                .unwrap(),
+            vec![QuestID(5, 1)]
+        );
+        assert!(QuestSelector::Title("no such quest".to_string())
+            .expand()
+            .is_err());
+    }
+}
@@ -21,8 +21,7 @@ use std::str::FromStr;
 
 use anyhow::Context;
 use boolinator::Boolinator;
-use lazy_regex::{lazy_regex, regex_captures, Regex};
-use once_cell::sync::Lazy;
+use itertools::Itertools;
 use strum::IntoEnumIterator;
 
 use crate::{
@@ -30,10 +29,9 @@ use crate::{
     data::{Monster, QuestID, Weapon},
     error::CommandError,
     model::response::Choices,
+    parser::QuestSelector,
 };
 
-static QUEST_ID_REGEX: Lazy<Regex> = lazy_regex!("([1-9])-([0-9])+");
-
 pub struct Validated<'a, Args, T>
 where
     Args: Iterator,
@@ -49,15 +47,18 @@ where
     <Args as Iterator>::Item: Clone + Into<String>,
 {
     pub fn parse(&self) -> anyhow::Result<Vec<QuestID>> {
-        self.accepted
+        Ok(self
+            .accepted
             .clone()
-            .map(|quest_id| -> anyhow::Result<QuestID> {
-                let quest_id: String = quest_id.into();
-                let (_, rank, number) = regex_captures!("([1-9])-([0-9])+", quest_id.as_str())
-                    .with_context(|| anyhow::anyhow!("regex_captures failed."))?;
-                Ok(QuestID(rank.parse::<u32>()?, number.parse::<u32>()?))
+            .map(|selector| -> anyhow::Result<Vec<QuestID>> {
+                let selector: String = selector.into();
+                Ok(selector.parse::<QuestSelector>()?.expand()?)
             })
-            .collect::<anyhow::Result<Vec<_>>>()
+            .collect::<anyhow::Result<Vec<_>>>()?
+            .into_iter()
+            .flatten()
+            .unique()
+            .collect())
     }
 }
 
@@ -148,28 +149,42 @@ where
     where
         T: SameAs<QuestID>,
     {
-        self.clone()
-            .all(|quest_id| {
-                let quest_id: String = quest_id.into();
-                QUEST_ID_REGEX.is_match(quest_id.as_str())
+        let selectors = self
+            .clone()
+            .map(|selector| {
+                let selector: String = selector.into();
+                let parsed = selector.parse::<QuestSelector>();
+                (selector, parsed)
             })
+            .collect_vec();
+
+        // First, every argument should be a valid selector.
+        let invalid_args = selectors
+            .iter()
+            .filter_map(|(selector, parsed)| parsed.is_err().as_some(selector))
+            .join(", ");
+        if !invalid_args.is_empty() {
+            return Err(CommandError::InvalidArgument { arg: invalid_args })
+                .with_context(|| anyhow::anyhow!("invalid quest selector."));
+        }
+
+        // Second, every selector should match at least one quest in the catalog.
+        let unknown_quests = selectors
+            .iter()
+            .filter_map(|(_, parsed)| parsed.as_ref().ok())
+            .filter(|selector| selector.expand().is_err())
+            .join(", ");
+        (unknown_quests.is_empty())
             .as_result_from(
                 || Validated {
                     accepted: self,
                     _type: Default::default(),
                 },
-                || {
-                    let invalid_args = self
-                        .clone()
-                        .filter_map(|quest_id| {
-                            let quest_id: String = quest_id.into();
-                            (!QUEST_ID_REGEX.is_match(quest_id.as_str())).as_some(quest_id)
-                        })
-                        .collect::<Vec<_>>()
-                        .join(", ");
-                    anyhow::Error::from(CommandError::InvalidArgument { arg: invalid_args })
+                || CommandError::NoSuchQuest {
+                    selector: unknown_quests,
                 },
             )
+            .with_context(|| anyhow::anyhow!("unknown quest."))
     }
 }
 